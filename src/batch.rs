@@ -0,0 +1,373 @@
+//! Running one note or many through the pipeline: routing with `provider = "auto"`,
+//! bounded concurrency over a batch, and the totals reported at the end.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::error::Failure;
+use crate::input::Input;
+use crate::providers::LlmProvider;
+use crate::{build_provider, route_provider};
+
+/// What a run of entries did, for the summary printed at the end.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RunStats {
+    /// Entries saved; previews and discarded entries don't count
+    pub entries: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Time spent waiting for the model
+    pub generation: Duration,
+}
+
+impl RunStats {
+    pub fn add(&mut self, other: &RunStats) {
+        self.entries += other.entries;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.generation += other.generation;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// The run summary, e.g. `Created 3 entries in 12.4s (generation 11.9s, 1234 tokens)`.
+pub fn format_stats(stats: &RunStats, elapsed: Duration) -> String {
+    let noun = if stats.entries == 1 {
+        "entry"
+    } else {
+        "entries"
+    };
+    let mut details = vec![format!("generation {:.1}s", stats.generation.as_secs_f64())];
+    if stats.total_tokens() > 0 {
+        details.push(format!("{} tokens", stats.total_tokens()));
+    }
+    format!(
+        "Created {} {} in {:.1}s ({})",
+        stats.entries,
+        noun,
+        elapsed.as_secs_f64(),
+        details.join(", ")
+    )
+}
+
+/// The `stats` object of an entry's `--json` output.
+pub fn stats_json(stats: &RunStats, elapsed: Duration) -> serde_json::Value {
+    let tokens = (stats.total_tokens() > 0).then(|| {
+        serde_json::json!({
+            "prompt": stats.prompt_tokens,
+            "completion": stats.completion_tokens,
+            "total": stats.total_tokens(),
+        })
+    });
+    serde_json::json!({
+        "entries": stats.entries,
+        "tokens": tokens,
+        "generation_ms": stats.generation.as_millis() as u64,
+        "elapsed_ms": elapsed.as_millis() as u64,
+    })
+}
+
+/// Creates one entry from a note: generates it and previews or saves it as the caller
+/// wants, writing the result to `out`.
+#[async_trait(?Send)]
+pub trait EntryRunner {
+    async fn run_entry(
+        &self,
+        config: &Config,
+        provider: &dyn LlmProvider,
+        content: &str,
+        out: &mut dyn Write,
+    ) -> Result<RunStats>;
+}
+
+/// `config` with `provider = "auto"` resolved for `content`, announcing the choice.
+pub fn route_note(config: &Config, content: &str) -> Config {
+    let routed = route_provider(config, Some(content.chars().count()));
+    if config.provider == "auto" {
+        progress!(routed, "Auto-selected provider: {}", routed.provider);
+    }
+    routed
+}
+
+/// The provider for every note, or `None` with `provider = "auto"`, where each note
+/// gets the one its length picks.
+pub async fn batch_provider(config: &Config) -> Result<Option<Box<dyn LlmProvider>>> {
+    if config.provider == "auto" {
+        return Ok(None);
+    }
+    let provider = build_provider(config)
+        .await
+        .map_err(Failure::ProviderUnavailable)?;
+    Ok(Some(provider))
+}
+
+/// Create one entry per input, carrying on past failures and summarizing at the end.
+/// Without a `provider`, each input is routed by its length (`provider = "auto"`).
+pub async fn run_batch(
+    runner: &dyn EntryRunner,
+    config: &Config,
+    provider: Option<&dyn LlmProvider>,
+    inputs: &[Input],
+    jobs: usize,
+) -> Result<RunStats> {
+    let results = process_inputs(runner, config, provider, inputs, jobs).await;
+    summarize_batch(config, inputs, &results)
+}
+
+/// Create one entry per input, returning each input's result in input order.
+///
+/// With more than one job, entries are generated concurrently; each entry's output is
+/// written in one piece when it finishes, and per-entry progress is left out so lines
+/// from different inputs don't interleave.
+pub async fn process_inputs(
+    runner: &dyn EntryRunner,
+    config: &Config,
+    provider: Option<&dyn LlmProvider>,
+    inputs: &[Input],
+    jobs: usize,
+) -> Vec<Result<RunStats>> {
+    let entry_config = Config {
+        quiet: config.quiet || jobs > 1,
+        ..config.clone()
+    };
+
+    run_bounded(inputs, jobs, |i, input| {
+        let entry_config = &entry_config;
+        async move {
+            progress!(config, "[{}/{}] {}", i + 1, inputs.len(), input.label());
+            let mut out = Vec::new();
+            let result = match (input.read(), provider) {
+                (Ok(content), Some(provider)) => {
+                    runner
+                        .run_entry(entry_config, provider, &content, &mut out)
+                        .await
+                }
+                (Ok(content), None) => {
+                    run_routed_entry(runner, entry_config, &content, &mut out).await
+                }
+                (Err(e), _) => Err(e),
+            };
+            std::io::stdout().write_all(&out)?;
+            if let Err(e) = &result {
+                eprintln!("Error: {}: {:#}", input.label(), e);
+            }
+            result
+        }
+    })
+    .await
+}
+
+/// `run_entry` with the provider `provider = "auto"` picks for `content`.
+async fn run_routed_entry(
+    runner: &dyn EntryRunner,
+    config: &Config,
+    content: &str,
+    out: &mut dyn Write,
+) -> Result<RunStats> {
+    let config = route_note(config, content);
+    let provider = build_provider(&config)
+        .await
+        .map_err(Failure::ProviderUnavailable)?;
+    runner
+        .run_entry(&config, provider.as_ref(), content, out)
+        .await
+}
+
+/// Report how many inputs succeeded and total their stats; an error names the failed ones.
+pub fn summarize_batch(
+    config: &Config,
+    inputs: &[Input],
+    results: &[Result<RunStats>],
+) -> Result<RunStats> {
+    // Listed in input order, whatever order they finished in
+    let failures: Vec<String> = inputs
+        .iter()
+        .zip(results)
+        .filter(|(_, result)| result.is_err())
+        .map(|(input, _)| input.label())
+        .collect();
+    progress!(
+        config,
+        "\n{} of {} input(s) succeeded",
+        inputs.len() - failures.len(),
+        inputs.len()
+    );
+    if failures.is_empty() {
+        let mut stats = RunStats::default();
+        for entry in results.iter().flatten() {
+            stats.add(entry);
+        }
+        Ok(stats)
+    } else {
+        Err(anyhow::anyhow!(
+            "{} input(s) failed: {}",
+            failures.len(),
+            failures.join(", ")
+        ))
+    }
+}
+
+/// Run `task` on every item with at most `jobs` in flight, returning the results in
+/// item order. A failing task doesn't affect the others.
+pub async fn run_bounded<'a, T, R, Fut>(
+    items: &'a [T],
+    jobs: usize,
+    task: impl Fn(usize, &'a T) -> Fut,
+) -> Vec<R>
+where
+    Fut: std::future::Future<Output = R>,
+{
+    let semaphore = tokio::sync::Semaphore::new(jobs.max(1));
+    let runs = items.iter().enumerate().map(|(i, item)| {
+        let semaphore = &semaphore;
+        let task = &task;
+        async move {
+            let _permit = semaphore
+                .acquire()
+                .await
+                .expect("semaphore is never closed");
+            task(i, item).await
+        }
+    });
+    futures_util::future::join_all(runs).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run_bounded_limits_concurrency_and_keeps_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let provider = providers::mock::MockProvider::new();
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let notes = ["first", "second", "third", "fourth", "fifth"];
+
+        // Earlier notes take longer, so they finish last
+        let results = run_bounded(&notes, 2, |i, note| {
+            let (provider, in_flight, peak) = (&provider, &in_flight, &peak);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100 * (5 - i as u64))).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                if *note == "third" {
+                    return Err(anyhow::anyhow!("boom"));
+                }
+                provider
+                    .generate(note, &providers::PromptOptions::default())
+                    .await
+            }
+        })
+        .await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        let contents: Vec<_> = results
+            .iter()
+            .map(|r| r.as_ref().map(|response| response.content.as_str()).ok())
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                Some("first"),
+                Some("second"),
+                None,
+                Some("fourth"),
+                Some("fifth")
+            ]
+        );
+        assert_eq!(provider.prompts().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_process_inputs_keeps_each_entrys_usage_with_jobs() {
+        struct Generate;
+
+        #[async_trait(?Send)]
+        impl EntryRunner for Generate {
+            async fn run_entry(
+                &self,
+                config: &Config,
+                provider: &dyn LlmProvider,
+                content: &str,
+                _out: &mut dyn Write,
+            ) -> Result<RunStats> {
+                let usage = crate::generate_entry(provider, config, content)
+                    .await?
+                    .usage
+                    .unwrap_or_default();
+                Ok(RunStats {
+                    entries: 1,
+                    prompt_tokens: usage.prompt_tokens,
+                    completion_tokens: usage.completion_tokens,
+                    ..RunStats::default()
+                })
+            }
+        }
+
+        let mut server = mockito::Server::new_async().await;
+        for (note, prompt_tokens) in [("Lunch with Jan", 100), ("Review the budget", 300)] {
+            server
+                .mock("POST", "/chat/completions")
+                .match_body(mockito::Matcher::Regex(note.to_string()))
+                .with_body(format!(
+                    r#"{{"choices": [{{"message": {{"role": "assistant", "content": "{{\"title\": \"note.md\", \"content\": \"{note}.\"}}"}}}}],
+                        "usage": {{"prompt_tokens": {prompt_tokens}, "completion_tokens": 10}}}}"#
+                ))
+                .create_async()
+                .await;
+        }
+        let mut config = Config {
+            provider: "openai".to_string(),
+            quiet: true,
+            ..Config::default()
+        };
+        config.openai.api_key = Some("test-key".to_string());
+        config.openai.base_url = server.url();
+        let provider = crate::create_provider(&config).unwrap();
+
+        let inputs = [
+            Input::Text("Lunch with Jan".to_string()),
+            Input::Text("Review the budget".to_string()),
+        ];
+        let results = process_inputs(&Generate, &config, Some(provider.as_ref()), &inputs, 2).await;
+        let prompt_tokens: Vec<u64> = results
+            .iter()
+            .map(|result| result.as_ref().unwrap().prompt_tokens)
+            .collect();
+        assert_eq!(prompt_tokens, [100, 300]);
+    }
+
+    #[test]
+    fn test_format_stats() {
+        let mut stats = RunStats {
+            entries: 1,
+            generation: Duration::from_millis(1900),
+            ..RunStats::default()
+        };
+        assert_eq!(
+            format_stats(&stats, Duration::from_millis(2100)),
+            "Created 1 entry in 2.1s (generation 1.9s)"
+        );
+
+        stats.add(&RunStats {
+            entries: 2,
+            prompt_tokens: 1000,
+            completion_tokens: 234,
+            generation: Duration::from_secs(10),
+        });
+        assert_eq!(
+            format_stats(&stats, Duration::from_millis(12_400)),
+            "Created 3 entries in 12.4s (generation 11.9s, 1234 tokens)"
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config;
+use crate::journal;
+use crate::providers::LlmResponse;
+
+/// On-disk cache of generated entries, one JSON file per request.
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedEntry {
+    /// Unix seconds when the entry was stored
+    created: u64,
+    response: LlmResponse,
+    /// `response.model`, which `LlmResponse` itself never reads back
+    #[serde(default)]
+    model: Option<String>,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    /// `cache/` in the config directory.
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(config::app_dir()?.join("cache"))
+    }
+
+    /// Key for a generation request, hashed from its `fingerprint`: everything that
+    /// changes the model's answer (see `request_fingerprint` in the library root).
+    ///
+    /// The hash is only stable within one build of the tool; a miss after an upgrade
+    /// just regenerates.
+    pub fn key(fingerprint: &serde_json::Value) -> String {
+        let mut hasher = DefaultHasher::new();
+        fingerprint.to_string().hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The stored response for `key`, unless it is missing, unreadable or older than the TTL.
+    pub fn get(&self, key: &str) -> Option<LlmResponse> {
+        let content = fs::read_to_string(self.path(key)).ok()?;
+        let entry: CachedEntry = serde_json::from_str(&content).ok()?;
+        let age = unix_now().saturating_sub(entry.created);
+        (age < self.ttl.as_secs()).then_some(LlmResponse {
+            model: entry.model,
+            ..entry.response
+        })
+    }
+
+    pub fn put(&self, key: &str, response: &LlmResponse) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create cache dir {}", self.dir.display()))?;
+        let entry = CachedEntry {
+            created: unix_now(),
+            response: response.clone(),
+            model: response.model.clone(),
+        };
+        let path = self.path(key);
+        journal::write_atomic(&path, &serde_json::to_string(&entry)?)
+            .with_context(|| format!("Failed to write cache entry {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::Usage;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn response() -> LlmResponse {
+        LlmResponse {
+            title: "call-jan.md".to_string(),
+            content: "Call Jan".to_string(),
+            tags: vec!["work".to_string()],
+            tasks: vec![],
+            model: None,
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn test_put_then_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().join("cache"), DAY);
+        let key = Cache::key(&serde_json::json!({"input": "Call Jan"}));
+
+        assert!(cache.get(&key).is_none());
+        let response = LlmResponse {
+            model: Some("llama3.2:1b".to_string()),
+            usage: Some(Usage {
+                prompt_tokens: 120,
+                completion_tokens: 40,
+            }),
+            ..response()
+        };
+        cache.put(&key, &response).unwrap();
+        let cached = cache.get(&key).unwrap();
+        assert_eq!(cached.title, "call-jan.md");
+        assert_eq!(cached.tags, vec!["work"]);
+        assert_eq!(cached.model.as_deref(), Some("llama3.2:1b"));
+        // Reusing the entry costs no tokens
+        assert_eq!(cached.usage, None);
+    }
+
+    #[test]
+    fn test_expired_entry_is_a_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().to_path_buf(), Duration::ZERO);
+        cache.put("k", &response()).unwrap();
+        assert!(cache.get("k").is_none());
+    }
+
+    #[test]
+    fn test_corrupt_entry_is_a_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().to_path_buf(), DAY);
+        fs::write(dir.path().join("k.json"), "not json").unwrap();
+        assert!(cache.get("k").is_none());
+    }
+
+    #[test]
+    fn test_key_follows_fingerprint() {
+        let fingerprint = serde_json::json!({"model": "gpt-4o-mini", "input": "Call Jan"});
+        let key = Cache::key(&fingerprint);
+        assert_eq!(key, Cache::key(&fingerprint.clone()));
+        assert_ne!(
+            key,
+            Cache::key(&serde_json::json!({"model": "gpt-4o", "input": "Call Jan"}))
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+
+/// Current time in the configured zone (local by default, UTC when `use_utc` is set).
+///
+/// All dated filenames and frontmatter timestamps go through this helper so they agree
+/// on which calendar day "now" is.
+pub fn now(use_utc: bool) -> DateTime<FixedOffset> {
+    in_zone(Utc::now(), use_utc)
+}
+
+/// Convert a UTC instant into the configured zone.
+pub fn in_zone(instant: DateTime<Utc>, use_utc: bool) -> DateTime<FixedOffset> {
+    if use_utc {
+        instant.fixed_offset()
+    } else {
+        in_local(instant, &Local)
+    }
+}
+
+/// `instant` in the `local` zone; the machine's zone outside tests.
+fn in_local<Tz: TimeZone>(instant: DateTime<Utc>, local: &Tz) -> DateTime<FixedOffset> {
+    instant.with_timezone(local).fixed_offset()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utc_keeps_date_near_midnight() {
+        let instant = Utc.with_ymd_and_hms(2026, 2, 25, 23, 30, 0).unwrap();
+        let now = in_zone(instant, true);
+        assert_eq!(
+            now.format("%Y-%m-%d_%H%M%S").to_string(),
+            "2026-02-25_233000"
+        );
+        assert_eq!(now.offset().local_minus_utc(), 0);
+    }
+
+    #[test]
+    fn test_local_date_differs_from_utc_near_midnight() {
+        let format = |now: DateTime<FixedOffset>| now.format("%Y-%m-%d_%H%M%S").to_string();
+
+        // Late evening in UTC is already tomorrow east of Greenwich...
+        let instant = Utc.with_ymd_and_hms(2026, 2, 25, 23, 30, 0).unwrap();
+        let berlin = FixedOffset::east_opt(3600).unwrap();
+        assert_eq!(format(in_local(instant, &berlin)), "2026-02-26_003000");
+
+        // ...and just after midnight in UTC is still yesterday west of it
+        let instant = Utc.with_ymd_and_hms(2026, 2, 26, 0, 30, 0).unwrap();
+        let new_york = FixedOffset::west_opt(5 * 3600).unwrap();
+        let now = in_local(instant, &new_york);
+        assert_eq!(format(now), "2026-02-25_193000");
+        assert_eq!(now.timestamp(), instant.timestamp());
+    }
+}
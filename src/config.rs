@@ -1,26 +1,236 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::{BufRead, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+use crate::keychain;
+use crate::providers::ollama;
+use crate::providers::{Cleanup, PromptOptions, TitleOptions, Tone};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     #[serde(default = "default_provider")]
     pub provider: String,
 
+    /// Use UTC instead of local time for dated filenames and timestamps
+    #[serde(default)]
+    pub use_utc: bool,
+
+    /// Don't print progress messages
+    #[serde(default)]
+    pub quiet: bool,
+
+    /// Maximum title length in characters before `.md`; 0 leaves only the 255-byte
+    /// filename limit
+    #[serde(default = "default_max_title_len")]
+    pub max_title_len: usize,
+
+    /// Keep at most this many tags per entry; 0 keeps all
+    #[serde(default = "default_max_tags")]
+    pub max_tags: usize,
+
+    /// Ask the model for tags; when off, entries get none (except `--tag` ones)
+    #[serde(default = "default_true")]
+    pub generate_tags: bool,
+
+    /// Controlled vocabulary: generated tags not in this list (compared
+    /// case-insensitively) are dropped; empty allows any tag
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_tags: Vec<String>,
+
+    /// Fail the entry when no (allowed) generated tag is left
+    #[serde(default)]
+    pub require_tag: bool,
+
+    /// How much the model may edit the note's text
+    #[serde(default)]
+    pub cleanup: Cleanup,
+
+    /// Register of the entry text: neutral keeps the note's own
+    #[serde(default)]
+    pub tone: Tone,
+
+    /// Word limit for entry content; longer content is cut at a sentence boundary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_words: Option<usize>,
+
+    /// Restrict titles to ASCII (transliterate accents, drop emoji and other scripts)
+    #[serde(default)]
+    pub ascii_only_titles: bool,
+
+    /// Lowercase and hyphenate titles; when off the model's title is kept as is, apart
+    /// from the file extension
+    #[serde(default = "default_true")]
+    pub sanitize_titles: bool,
+
+    /// Prefix titles with the current date (YYYY-MM-DD-)
+    #[serde(default)]
+    pub date_prefix: bool,
+
+    /// Prepend YAML frontmatter (title, date, tags) to saved entries
+    #[serde(default)]
+    pub frontmatter: bool,
+
+    /// Record the provider, model, temperature and time that produced each new entry
+    #[serde(default)]
+    pub record_metadata: bool,
+
+    /// Line added to the end of every saved entry; `{date}` and `{time}` are filled in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+
+    /// Retry, then fail, when the model answers in a different language than the note
+    #[serde(default)]
+    pub enforce_language: bool,
+
+    /// Check that the provider is reachable before generating; when off, a connection
+    /// problem surfaces from the request itself
+    #[serde(default = "default_true")]
+    pub precheck: bool,
+
+    /// Reuse the stored response when the same note is generated again
+    #[serde(default)]
+    pub cache: bool,
+
+    /// How long cached responses stay valid, in seconds
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+
+    /// End saved content with a newline
+    #[serde(default = "default_true")]
+    pub trailing_newline: bool,
+
+    /// Line endings for saved content
+    #[serde(default)]
+    pub line_ending: LineEnding,
+
+    /// What to do when today's journal already has an entry with the same title
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+
+    /// Copy an entry to `<file>.bak` before it is rewritten: `--append`, the daily file, or
+    /// tags added with `--tags-only --append`
+    #[serde(default = "default_true")]
+    pub backup: bool,
+
+    /// Warn when a new entry is nearly identical to a recent one (compared by embeddings)
+    #[serde(default)]
+    pub dedupe: bool,
+
+    /// Keep an index of entry tags in the config directory for `list --tag`
+    #[serde(default)]
+    pub tag_index: bool,
+
+    /// Append every note to today's `YYYY-MM-DD` file under a `## HH:MM` heading
+    /// instead of creating a titled entry
+    #[serde(default)]
+    pub daily_mode: bool,
+
+    /// Cosine similarity (0.0 - 1.0) at which an entry counts as a duplicate
+    #[serde(default = "default_dedupe_threshold")]
+    pub dedupe_threshold: f32,
+
+    /// With `provider = "auto"`, notes this long (in characters) or longer go to the
+    /// cloud provider instead of Ollama
+    #[serde(default = "default_auto_threshold_chars")]
+    pub auto_threshold_chars: usize,
+
     #[serde(default)]
     pub ollama: OllamaConfig,
 
     #[serde(default)]
     pub openai: OpenAiConfig,
+
+    #[serde(default)]
+    pub anthropic: AnthropicConfig,
+
+    #[serde(default)]
+    pub retry: RetryConfig,
+
+    #[serde(default)]
+    pub prompt: PromptConfig,
+
+    #[serde(default)]
+    pub journal: JournalConfig,
+
+    /// Named entry templates (added to, or overriding, the built-in ones)
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             provider: default_provider(),
+            use_utc: false,
+            quiet: false,
+            max_title_len: default_max_title_len(),
+            max_tags: default_max_tags(),
+            generate_tags: true,
+            allowed_tags: vec![],
+            require_tag: false,
+            cleanup: Cleanup::default(),
+            tone: Tone::default(),
+            max_words: None,
+            ascii_only_titles: false,
+            sanitize_titles: true,
+            date_prefix: false,
+            frontmatter: false,
+            record_metadata: false,
+            footer: None,
+            enforce_language: false,
+            precheck: true,
+            cache: false,
+            cache_ttl_secs: default_cache_ttl_secs(),
+            trailing_newline: true,
+            line_ending: LineEnding::default(),
+            on_conflict: OnConflict::default(),
+            backup: true,
+            dedupe: false,
+            tag_index: false,
+            daily_mode: false,
+            dedupe_threshold: default_dedupe_threshold(),
+            auto_threshold_chars: default_auto_threshold_chars(),
             ollama: OllamaConfig::default(),
             openai: OpenAiConfig::default(),
+            anthropic: AnthropicConfig::default(),
+            retry: RetryConfig::default(),
+            prompt: PromptConfig::default(),
+            journal: JournalConfig::default(),
+            templates: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OnConflict {
+    /// Save as `title-2.md`, `title-3.md`, ...
+    #[default]
+    Suffix,
+    /// Save under the same title anyway
+    Overwrite,
+    /// Refuse to save
+    Error,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
         }
     }
 }
@@ -32,6 +242,61 @@ pub struct OllamaConfig {
 
     #[serde(default = "default_ollama_model")]
     pub model: String,
+
+    /// Models tried in order when `model` gives no parseable entry or is not found
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_models: Vec<String>,
+
+    /// Sampling temperature for entry generation (0.0 - 2.0); unset keeps the
+    /// model's own default from its Modelfile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    /// Request timeout for generation, in seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Upper bound on generated tokens; unset leaves the provider default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    /// Proxy URL for this provider, overriding HTTP(S)_PROXY
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    /// Stream tokens to stderr while generating
+    #[serde(default)]
+    pub stream: bool,
+
+    /// Pull the model via /api/pull when Ollama reports it missing
+    #[serde(default)]
+    pub auto_pull: bool,
+
+    /// How long Ollama keeps the model loaded after a request: a duration such as "10m",
+    /// or a number of seconds ("-1" keeps it loaded, "0" unloads it right away)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+
+    /// Context window size in tokens; unset uses the model default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+
+    /// Stop sequences (`options.stop`) that end generation, e.g. after the JSON
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+
+    /// System prompt for this provider, replacing the built-in one; `[prompt] system`
+    /// and --system-prompt still win
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    /// Extra Ollama `options` (top_k, repeat_penalty, ...); settings above win
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra_options: serde_json::Map<String, serde_json::Value>,
+
+    /// Model for `dedupe` embeddings
+    #[serde(default = "default_ollama_embedding_model")]
+    pub embedding_model: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -42,8 +307,213 @@ pub struct OpenAiConfig {
     #[serde(default = "default_openai_model")]
     pub model: String,
 
+    /// Models tried in order when `model` gives no parseable entry or is not found
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_models: Vec<String>,
+
+    /// Sampling temperature for entry generation (0.0 - 2.0)
+    #[serde(default = "default_temperature")]
+    pub temperature: f32,
+
+    /// Request timeout for generation, in seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Upper bound on generated tokens; unset leaves the provider default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    /// Stop sequences (`stop`, up to 4) that end generation, e.g. after the JSON
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+
+    /// Proxy URL for this provider, overriding HTTP(S)_PROXY
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+
+    /// Sent as `OpenAI-Organization` (or set OPENAI_ORG_ID)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+
+    /// Sent as `OpenAI-Project` (or set OPENAI_PROJECT_ID)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+
+    /// Target Azure OpenAI: `base_url` is the resource endpoint, `model` the deployment
+    /// name, and the key (or AZURE_OPENAI_API_KEY) is sent as `api-key`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub azure: bool,
+
+    /// Azure `api-version` query parameter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+
+    /// Set to false for OpenAI-compatible servers without auth (llama-server, LM Studio,
+    /// vLLM); requests then go out without an `Authorization` header when no key is set
+    #[serde(default = "default_true")]
+    pub require_key: bool,
+
+    /// System prompt for this provider, replacing the built-in one; `[prompt] system`
+    /// and --system-prompt still win
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    /// Extra top-level request fields (top_p, presence_penalty, ...); settings above win
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra_options: serde_json::Map<String, serde_json::Value>,
+
+    /// Model for `dedupe` embeddings
+    #[serde(default = "default_openai_embedding_model")]
+    pub embedding_model: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnthropicConfig {
+    #[serde(default = "default_anthropic_url")]
+    pub base_url: String,
+
+    #[serde(default = "default_anthropic_model")]
+    pub model: String,
+
+    /// Sampling temperature for entry generation (0.0 - 2.0)
+    #[serde(default = "default_temperature")]
+    pub temperature: f32,
+
+    /// Request timeout for generation, in seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Upper bound on generated tokens; unset leaves the provider default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    /// Proxy URL for this provider, overriding HTTP(S)_PROXY
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
     #[serde(skip_serializing)]
     pub api_key: Option<String>,
+
+    /// System prompt for this provider, replacing the built-in one; `[prompt] system`
+    /// and --system-prompt still win
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    /// Extra top-level request fields (top_p, top_k, ...); settings above win
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra_options: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Retry policy for transient provider failures (429, 5xx, connection errors)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetryConfig {
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    #[serde(default = "default_base_delay_ms")]
+    pub base_delay_ms: u64,
+
+    /// Most requests sent to a provider per minute; unset means no limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_per_min: Option<u32>,
+
+    /// Times to ask again, insisting on JSON, when the model's answer can't be parsed
+    #[serde(default = "default_json_retries")]
+    pub json_retries: u32,
+}
+
+/// Command used to save entries (file-journal by default)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalConfig {
+    #[serde(default = "default_journal_command")]
+    pub command: String,
+
+    /// Arguments for creating an entry; `{title}` and `{content}` are substituted
+    #[serde(default = "default_new_args")]
+    pub new_args: Vec<String>,
+
+    /// Write the content to the command's stdin instead of passing it as `{content}`
+    #[serde(default = "default_true")]
+    pub content_via_stdin: bool,
+
+    /// Journal to use instead of file-journal's default, passed to the command as `--dir`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal_dir: Option<PathBuf>,
+
+    /// Extension of entry files, without the dot (e.g. `txt` or `org`)
+    #[serde(default = "default_extension")]
+    pub extension: String,
+
+    /// file-journal versions known to work, e.g. `>=0.1.0, <1.0.0`; `doctor` warns when
+    /// the installed one is outside them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_journal_version: Option<String>,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            command: default_journal_command(),
+            new_args: default_new_args(),
+            content_via_stdin: true,
+            journal_dir: None,
+            extension: default_extension(),
+            file_journal_version: None,
+        }
+    }
+}
+
+/// Custom system prompt for entry generation; the built-in prompt is used when unset
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PromptConfig {
+    /// Inline system prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+
+    /// File to read the system prompt from; `system` wins when both are set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_file: Option<PathBuf>,
+
+    /// Template applied to every entry (override with --template)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+/// Templates available without any `[templates]` config.
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "daily",
+        "Begin with a line `Mood: <one word>`, followed by exactly three bullet points covering the day.",
+    ),
+    (
+        "idea",
+        "Begin with a one-sentence summary of the idea, then `## Details` and `## Next steps` sections.",
+    ),
+    (
+        "meeting",
+        "Use the sections `## Attendees` (people mentioned), `## Notes` and `## Action items` (also list them as tasks).",
+    ),
+];
+
+impl PromptConfig {
+    /// Resolve the configured system prompt, reading `system_file` if needed.
+    pub fn system_prompt(&self) -> Result<Option<String>> {
+        if let Some(system) = &self.system {
+            return Ok(Some(system.clone()));
+        }
+        match &self.system_file {
+            Some(path) => {
+                let text = fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read system prompt from {}", path.display())
+                })?;
+                Ok(Some(text.trim().to_string()))
+            }
+            None => Ok(None),
+        }
+    }
 }
 
 impl Default for OllamaConfig {
@@ -51,6 +521,19 @@ impl Default for OllamaConfig {
         Self {
             base_url: default_ollama_url(),
             model: default_ollama_model(),
+            fallback_models: vec![],
+            temperature: None,
+            timeout_secs: default_timeout_secs(),
+            max_tokens: None,
+            proxy: None,
+            stream: false,
+            auto_pull: false,
+            keep_alive: None,
+            num_ctx: None,
+            stop: vec![],
+            system_prompt: None,
+            extra_options: serde_json::Map::new(),
+            embedding_model: default_ollama_embedding_model(),
         }
     }
 }
@@ -60,15 +543,173 @@ impl Default for OpenAiConfig {
         Self {
             base_url: default_openai_url(),
             model: default_openai_model(),
+            fallback_models: vec![],
+            temperature: default_temperature(),
+            timeout_secs: default_timeout_secs(),
+            max_tokens: None,
+            stop: vec![],
+            proxy: None,
             api_key: None,
+            organization: None,
+            project: None,
+            azure: false,
+            api_version: None,
+            require_key: true,
+            system_prompt: None,
+            extra_options: serde_json::Map::new(),
+            embedding_model: default_openai_embedding_model(),
+        }
+    }
+}
+
+impl OpenAiConfig {
+    /// Environment variable (and keychain account) holding the API key.
+    pub fn api_key_var(&self) -> &'static str {
+        if self.azure {
+            "AZURE_OPENAI_API_KEY"
+        } else {
+            "OPENAI_API_KEY"
+        }
+    }
+
+    /// Whether requests can be made: a key is set, or the server doesn't need one.
+    pub fn has_credentials(&self) -> bool {
+        self.api_key.is_some() || !self.require_key
+    }
+}
+
+impl Default for AnthropicConfig {
+    fn default() -> Self {
+        Self {
+            base_url: default_anthropic_url(),
+            model: default_anthropic_model(),
+            temperature: default_temperature(),
+            timeout_secs: default_timeout_secs(),
+            max_tokens: None,
+            proxy: None,
+            api_key: None,
+            system_prompt: None,
+            extra_options: serde_json::Map::new(),
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            base_delay_ms: default_base_delay_ms(),
+            rate_limit_per_min: None,
+            json_retries: default_json_retries(),
         }
     }
 }
 
+fn default_max_title_len() -> usize {
+    80
+}
+
+fn default_max_tags() -> usize {
+    3
+}
+
+fn default_dedupe_threshold() -> f32 {
+    0.92
+}
+
+fn default_extension() -> String {
+    "md".to_string()
+}
+
+fn default_auto_threshold_chars() -> usize {
+    2000
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+/// The base config directory. Per the XDG spec a relative or empty `XDG_CONFIG_HOME`
+/// is ignored. `~/.config` is kept on macOS and Windows too, where existing configs live.
+fn config_dir(xdg_config_home: Option<std::ffi::OsString>, home: &Path) -> PathBuf {
+    xdg_config_home
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| home.join(".config"))
+}
+
+/// Names the keys of a config file that no setting reads, so a typo like `tempreature`
+/// doesn't go unnoticed. Parse errors are left for the real load to report.
+fn unknown_keys_warning(path: &Path, content: &str) -> Option<String> {
+    let unknown = Config::unknown_keys(content).ok()?;
+    (!unknown.is_empty()).then(|| {
+        format!(
+            "Ignoring unknown config keys in {}: {}",
+            path.display(),
+            unknown.join(", ")
+        )
+    })
+}
+
+/// Set by `--config-dir`; wins over `JOURNAL_AI_HOME`.
+static APP_DIR_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Relocate the config file and all state for this process (`--config-dir`).
+pub fn set_app_dir(dir: Option<PathBuf>) {
+    *APP_DIR_OVERRIDE
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = dir;
+}
+
+/// The directory holding the config file and all state (cache, embedding index,
+/// import state): `--config-dir`, else `JOURNAL_AI_HOME`, else `journal-ai` under the
+/// base config directory. Every path under it goes through here.
+pub fn app_dir() -> Result<PathBuf> {
+    let override_dir = APP_DIR_OVERRIDE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    let home = dirs::home_dir().context("Could not determine home directory")?;
+    Ok(resolve_app_dir(
+        override_dir,
+        std::env::var_os("JOURNAL_AI_HOME"),
+        std::env::var_os("XDG_CONFIG_HOME"),
+        &home,
+    ))
+}
+
+fn resolve_app_dir(
+    override_dir: Option<PathBuf>,
+    journal_ai_home: Option<std::ffi::OsString>,
+    xdg_config_home: Option<std::ffi::OsString>,
+    home: &Path,
+) -> PathBuf {
+    override_dir
+        .or_else(|| journal_ai_home.map(PathBuf::from))
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| config_dir(xdg_config_home, home).join("journal-ai"))
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn default_provider() -> String {
     "ollama".to_string()
 }
 
+fn default_journal_command() -> String {
+    "file-journal".to_string()
+}
+
+fn default_new_args() -> Vec<String> {
+    vec![
+        "new".to_string(),
+        "{title}".to_string(),
+        "{content}".to_string(),
+    ]
+}
+
 fn default_ollama_url() -> String {
     "http://localhost:11434".to_string()
 }
@@ -77,6 +718,10 @@ fn default_ollama_model() -> String {
     "llama3.2".to_string()
 }
 
+fn default_ollama_embedding_model() -> String {
+    "nomic-embed-text".to_string()
+}
+
 fn default_openai_url() -> String {
     "https://api.openai.com/v1".to_string()
 }
@@ -85,17 +730,318 @@ fn default_openai_model() -> String {
     "gpt-4o-mini".to_string()
 }
 
+fn default_openai_embedding_model() -> String {
+    "text-embedding-3-small".to_string()
+}
+
+fn default_temperature() -> f32 {
+    0.1
+}
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_base_delay_ms() -> u64 {
+    500
+}
+
+fn default_json_retries() -> u32 {
+    1
+}
+
+fn default_anthropic_url() -> String {
+    "https://api.anthropic.com/v1".to_string()
+}
+
+fn default_anthropic_model() -> String {
+    "claude-3-5-sonnet-latest".to_string()
+}
+
+/// Temperatures outside 0.0 - 2.0 are rejected by the provider APIs.
+/// Providers `provider` may name.
+const KNOWN_PROVIDERS: &[&str] = &[
+    "ollama",
+    "openai",
+    "anthropic",
+    "none",
+    "auto",
+    #[cfg(feature = "mock")]
+    "mock",
+];
+
+/// An absolute http(s) URL.
+pub(crate) fn validate_url(url: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url)?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(anyhow!("expected http or https, got {}", scheme)),
+    }
+}
+
+pub fn validate_temperature(value: f32) -> Result<f32> {
+    if (0.0..=2.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(anyhow::anyhow!(
+            "Temperature must be between 0.0 and 2.0, got {}",
+            value
+        ))
+    }
+}
+
+/// Substitute `${VAR}` placeholders in a config string. `field` names the setting in errors.
+/// `$${` is a literal `${`, for prompts and templates that need one.
+fn expand_env_vars(
+    value: &str,
+    field: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| anyhow!("Unterminated ${{...}} in config field '{}'", field))?;
+        let name = &rest[start + 2..end];
+        let substitute = lookup(name).ok_or_else(|| {
+            anyhow!(
+                "Environment variable '{}' used in config field '{}' is not set",
+                name,
+                field
+            )
+        })?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&substitute);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Expand `${VAR}` in every string of a parsed config, tracking the dotted field path.
+fn expand_env_in_value(
+    value: &mut toml::Value,
+    field: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<()> {
+    match value {
+        toml::Value::String(s) => *s = expand_env_vars(s, field, lookup)?,
+        toml::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                expand_env_in_value(item, &format!("{}[{}]", field, i), lookup)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                let path = if field.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", field, key)
+                };
+                expand_env_in_value(item, &path, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Remove the `[profiles]` table from a parsed config.
+fn take_profiles(value: &mut toml::Value) -> Result<toml::map::Map<String, toml::Value>> {
+    match value
+        .as_table_mut()
+        .and_then(|table| table.remove("profiles"))
+    {
+        None => Ok(toml::map::Map::new()),
+        Some(toml::Value::Table(profiles)) => {
+            if let Some((name, _)) = profiles.iter().find(|(_, profile)| !profile.is_table()) {
+                return Err(anyhow!("Profile '{}' must be a table", name));
+            }
+            Ok(profiles)
+        }
+        Some(_) => Err(anyhow!(
+            "[profiles] must be a table of [profiles.<name>] tables"
+        )),
+    }
+}
+
+/// Merge the selected profile over the base config and drop the `[profiles]` table.
+fn select_profile(value: &mut toml::Value, profile: Option<&str>) -> Result<()> {
+    let mut profiles = take_profiles(value)?;
+    let Some(name) = profile else {
+        return Ok(());
+    };
+    let overrides = profiles.remove(name).ok_or_else(|| {
+        let available: Vec<&str> = profiles.keys().map(String::as_str).collect();
+        anyhow!(
+            "Unknown profile '{}'. Available: {}",
+            name,
+            if available.is_empty() {
+                "none (add a [profiles.<name>] table to the config)".to_string()
+            } else {
+                available.join(", ")
+            }
+        )
+    })?;
+    merge_toml(value, overrides);
+    Ok(())
+}
+
+/// Overlay `overrides` on `base`; tables merge key by key, anything else is replaced.
+fn merge_toml(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Models `init` offers when OpenAI is selected.
+const OPENAI_MODELS: &[&str] = &["gpt-4o-mini", "gpt-4o", "gpt-4.1-mini", "gpt-4.1"];
+
+/// Ask for a model by number from `choices`, or by name. An empty answer (or a number
+/// out of range) keeps `default`; without choices it is a free-text prompt.
+fn prompt_model(
+    choices: &[String],
+    default: &str,
+    reader: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<String> {
+    if choices.is_empty() {
+        write!(out, "Model name [{}]: ", default)?;
+    } else {
+        writeln!(out, "Select model:")?;
+        for (i, model) in choices.iter().enumerate() {
+            writeln!(out, "{}. {}", i + 1, model)?;
+        }
+        write!(out, "Number or model name [{}]: ", default)?;
+    }
+    out.flush()?;
+
+    let mut answer = String::new();
+    reader.read_line(&mut answer)?;
+    let answer = answer.trim();
+    let model = match answer.parse::<usize>() {
+        Ok(n) if (1..=choices.len()).contains(&n) => &choices[n - 1],
+        Ok(_) => default,
+        Err(_) if answer.is_empty() => default,
+        Err(_) => answer,
+    };
+    Ok(model.to_string())
+}
+
+/// During `init`, offer to keep the key in the OS keychain so it survives the session.
+fn offer_keychain(account: &str, key: &str) -> Result<()> {
+    if !keychain::available() || key.is_empty() {
+        return Ok(());
+    }
+    println!("Store the key in the OS keychain? [y/N]");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        match keychain::set(account, key) {
+            Ok(()) => println!("Key stored in the OS keychain"),
+            // No usable keychain: the env var still works
+            Err(e) => println!("{:#}; set {} instead", e, account),
+        }
+    }
+    Ok(())
+}
+
+/// An API key reduced to its last four characters, e.g. `****abcd`.
+fn mask_api_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() < 8 {
+        return "****".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{}", tail)
+}
+
 impl Config {
-    pub fn load(config_path: Option<PathBuf>) -> Result<Self> {
+    /// Load the config file, with the `[profiles.<name>]` table named by `profile` merged
+    /// over it. API keys come from the file and the environment; keys kept in the
+    /// keychain are loaded separately with [`Config::load_keychain_keys`].
+    pub fn load(config_path: Option<PathBuf>, profile: Option<&str>) -> Result<Self> {
+        let content = match Self::find(config_path)? {
+            Some(path) => {
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read config from {}", path.display()))?;
+                if let Some(warning) = unknown_keys_warning(&path, &content) {
+                    eprintln!("Warning: {}", warning);
+                }
+                content
+            }
+            // Default config with env vars
+            None => String::new(),
+        };
+        let mut config = Self::parse(&content, profile)?;
+        config.apply_env_overrides(|name| std::env::var(name).ok());
+        config.load_api_keys();
+        Ok(config)
+    }
+
+    /// `JOURNAL_AI_PROVIDER` and `JOURNAL_AI_MODEL` over the file and defaults, for
+    /// deployments configured without a file. Command-line flags still win.
+    fn apply_env_overrides(&mut self, var: impl Fn(&str) -> Option<String>) {
+        if let Some(provider) = var("JOURNAL_AI_PROVIDER").filter(|p| !p.is_empty()) {
+            self.provider = provider;
+        }
+        if let Some(model) = var("JOURNAL_AI_MODEL").filter(|m| !m.is_empty()) {
+            if !self.set_model(model) {
+                tracing::warn!("JOURNAL_AI_MODEL ignored for provider '{}'", self.provider);
+            }
+        }
+    }
+
+    /// Set the model of the selected provider. `false` when the provider has no model.
+    pub fn set_model(&mut self, model: String) -> bool {
+        match self.provider.as_str() {
+            "ollama" => self.ollama.model = model,
+            "openai" => self.openai.model = model,
+            "anthropic" => self.anthropic.model = model,
+            _ => return false,
+        }
+        true
+    }
+
+    fn parse(content: &str, profile: Option<&str>) -> Result<Self> {
+        let mut value: toml::Value =
+            toml::from_str(content).with_context(|| "Failed to parse config TOML")?;
+        select_profile(&mut value, profile)?;
+        expand_env_in_value(&mut value, "", &|name| std::env::var(name).ok())?;
+        value
+            .try_into()
+            .with_context(|| "Failed to parse config TOML")
+    }
+
+    /// The file `load` reads: an existing explicit path, else `.journal-ai.toml`, else
+    /// the default location. `None` when there is no config file.
+    pub fn find(config_path: Option<PathBuf>) -> Result<Option<PathBuf>> {
         // If explicit path provided, use that
         if let Some(path) = config_path {
             if path.exists() {
-                let content = fs::read_to_string(&path)
-                    .with_context(|| format!("Failed to read config from {}", path.display()))?;
-                let mut config: Config =
-                    toml::from_str(&content).with_context(|| "Failed to parse config TOML")?;
-                config.load_api_keys();
-                return Ok(config);
+                return Ok(Some(path));
             }
         }
 
@@ -104,44 +1050,326 @@ impl Config {
             Path::new(".journal-ai.toml").to_path_buf(),
             Self::default_config_path()?,
         ];
+        Ok(config_paths.into_iter().find(|path| path.exists()))
+    }
 
-        for path in config_paths {
-            if path.exists() {
-                let content = fs::read_to_string(&path)
-                    .with_context(|| format!("Failed to read config from {}", path.display()))?;
-                let mut config: Config =
-                    toml::from_str(&content).with_context(|| "Failed to parse config TOML")?;
-                config.load_api_keys();
-                return Ok(config);
+    /// Keys in a config file that no setting reads, e.g. a misspelled `provder`.
+    pub fn unknown_keys(content: &str) -> Result<Vec<String>> {
+        let mut value: toml::Value =
+            toml::from_str(content).with_context(|| "Failed to parse config TOML")?;
+        let profiles = take_profiles(&mut value)?;
+
+        let mut unknown = Vec::new();
+        let _: Config = serde_ignored::deserialize(value, |path| unknown.push(path.to_string()))
+            .with_context(|| "Failed to parse config TOML")?;
+        for (name, profile) in profiles {
+            let _: Config = serde_ignored::deserialize(profile, |path| {
+                unknown.push(format!("profiles.{}.{}", name, path))
+            })
+            .with_context(|| format!("Failed to parse profile '{}'", name))?;
+        }
+        Ok(unknown)
+    }
+
+    /// Check values that serde cannot validate on its own, returning every problem found.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !KNOWN_PROVIDERS.contains(&self.provider.as_str()) {
+            problems.push(format!(
+                "Unknown provider '{}'. Use one of: {}",
+                self.provider,
+                KNOWN_PROVIDERS.join(", ")
+            ));
+        }
+
+        let sections = [
+            (
+                "ollama",
+                self.ollama.temperature,
+                &self.ollama.base_url,
+                &self.ollama.proxy,
+            ),
+            (
+                "openai",
+                Some(self.openai.temperature),
+                &self.openai.base_url,
+                &self.openai.proxy,
+            ),
+            (
+                "anthropic",
+                Some(self.anthropic.temperature),
+                &self.anthropic.base_url,
+                &self.anthropic.proxy,
+            ),
+        ];
+        for (section, temperature, base_url, proxy) in sections {
+            if let Some(Err(e)) = temperature.map(validate_temperature) {
+                problems.push(format!("Invalid [{}] temperature: {}", section, e));
+            }
+            if let Err(e) = validate_url(base_url) {
+                problems.push(format!(
+                    "Invalid [{}] base_url '{}': {}",
+                    section, base_url, e
+                ));
+            }
+            if let Some(proxy) = proxy {
+                if let Err(e) = validate_url(proxy) {
+                    problems.push(format!("Invalid [{}] proxy '{}': {}", section, proxy, e));
+                }
             }
         }
 
-        // Return default config with env vars
-        let mut config = Config::default();
-        config.load_api_keys();
-        Ok(config)
+        if let Some(file) = &self.prompt.system_file {
+            if !file.is_file() {
+                problems.push(format!(
+                    "[prompt] system_file {} does not exist",
+                    file.display()
+                ));
+            }
+        }
+        if let Some(template) = &self.prompt.template {
+            if let Err(e) = self.template(template) {
+                problems.push(format!("[prompt] template: {}", e));
+            }
+        }
+
+        let extension = &self.journal.extension;
+        if extension.is_empty()
+            || !extension
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        {
+            problems.push(format!(
+                "Invalid [journal] extension '{}': use lowercase letters and digits without the dot, e.g. \"txt\"",
+                extension
+            ));
+        }
+        if let Some(supported) = &self.journal.file_journal_version {
+            if let Err(e) = crate::journal::Version::new(0, 0, 0).matches(supported) {
+                problems.push(format!("Invalid [journal] file_journal_version: {}", e));
+            }
+        }
+
+        problems
+    }
+
+    /// Names of all templates, built-in and configured, sorted.
+    pub fn template_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = BUILTIN_TEMPLATES
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .chain(self.templates.keys().cloned())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Look up a template by name; configured templates shadow built-in ones.
+    pub fn template(&self, name: &str) -> Result<String> {
+        if let Some(text) = self.templates.get(name) {
+            return Ok(text.clone());
+        }
+        BUILTIN_TEMPLATES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, text)| text.to_string())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown template '{}'. Available templates: {}",
+                    name,
+                    self.template_names().join(", ")
+                )
+            })
+    }
+
+    /// Resolve the system prompt and template for generation.
+    pub fn prompt_options(&self) -> Result<PromptOptions> {
+        Ok(PromptOptions {
+            system: self.prompt.system_prompt()?,
+            template: self
+                .prompt
+                .template
+                .as_deref()
+                .map(|name| self.template(name))
+                .transpose()?,
+            max_tags: self.max_tags,
+            no_tags: !self.generate_tags,
+            max_words: self.max_words,
+            language: None,
+            strict_json: false,
+            cleanup: self.cleanup,
+            tone: self.tone,
+        })
+    }
+
+    /// Settings section of the selected provider (model, URL, temperature, limits,
+    /// extra options) as JSON, without the API key; null for other providers.
+    pub fn provider_settings(&self) -> serde_json::Value {
+        let section = match self.provider.as_str() {
+            "ollama" => serde_json::to_value(&self.ollama),
+            "openai" => serde_json::to_value(&self.openai),
+            "anthropic" => serde_json::to_value(&self.anthropic),
+            _ => Ok(serde_json::Value::Null),
+        };
+        section.unwrap_or_default()
+    }
+
+    /// Model of the selected provider, if the provider is a known one.
+    pub fn model(&self) -> Option<&str> {
+        match self.provider.as_str() {
+            "ollama" => Some(&self.ollama.model),
+            "openai" => Some(&self.openai.model),
+            "anthropic" => Some(&self.anthropic.model),
+            _ => None,
+        }
+    }
+
+    /// Timeout and proxy of the selected provider, for other requests made for a run
+    /// (like fetching `--url`); the default timeout and no proxy without one.
+    pub fn http_settings(&self) -> (Duration, Option<&str>) {
+        let (secs, proxy) = match self.provider.as_str() {
+            "ollama" => (self.ollama.timeout_secs, self.ollama.proxy.as_deref()),
+            "openai" => (self.openai.timeout_secs, self.openai.proxy.as_deref()),
+            "anthropic" => (self.anthropic.timeout_secs, self.anthropic.proxy.as_deref()),
+            _ => (default_timeout_secs(), None),
+        };
+        (Duration::from_secs(secs), proxy)
+    }
+
+    /// `system_prompt` configured for the selected provider.
+    pub fn system_prompt(&self) -> Option<&str> {
+        match self.provider.as_str() {
+            "ollama" => self.ollama.system_prompt.as_deref(),
+            "openai" => self.openai.system_prompt.as_deref(),
+            "anthropic" => self.anthropic.system_prompt.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Temperature of the selected provider; `None` for `none` and unknown providers.
+    pub fn temperature(&self) -> Option<f32> {
+        match self.provider.as_str() {
+            "ollama" => self.ollama.temperature,
+            "openai" => Some(self.openai.temperature),
+            "anthropic" => Some(self.anthropic.temperature),
+            _ => None,
+        }
+    }
+
+    pub fn title_options(&self) -> TitleOptions {
+        TitleOptions {
+            max_len: self.max_title_len,
+            ascii_only: self.ascii_only_titles,
+            sanitize: self.sanitize_titles,
+            extension: self.journal.extension.clone(),
+            ..TitleOptions::default()
+        }
+    }
+
+    /// API keys to show in `config show`, masked, by config section.
+    fn masked_api_keys(&self) -> Vec<(&'static str, String)> {
+        [
+            ("openai", &self.openai.api_key),
+            ("anthropic", &self.anthropic.api_key),
+        ]
+        .into_iter()
+        .filter_map(|(section, key)| Some((section, mask_api_key(key.as_deref()?))))
+        .collect()
     }
 
+    /// The effective config as TOML, with API keys masked.
+    pub fn to_redacted_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(&toml::Value::try_from(
+            self.to_redacted_json()?,
+        )?)?)
+    }
+
+    /// The effective config as JSON, with API keys masked.
+    pub fn to_redacted_json(&self) -> Result<serde_json::Value> {
+        // Through text, so f32 settings print as written (0.1, not 0.10000000149011612)
+        let mut value: serde_json::Value = serde_json::from_str(&serde_json::to_string(self)?)?;
+        for (section, key) in self.masked_api_keys() {
+            value[section]["api_key"] = serde_json::Value::String(key);
+        }
+        Ok(value)
+    }
+
+    /// API keys from the environment when the config file has none. The keychain is
+    /// left to [`Config::load_keychain_keys`], so it is only asked for keys in use.
     fn load_api_keys(&mut self) {
-        // Load OpenAI API key from environment if not in config
-        if self.openai.api_key.is_none() {
-            if let Ok(key) = std::env::var("OPENAI_API_KEY") {
-                self.openai.api_key = Some(key);
-            }
+        let no_keychain = || None;
+        let var = self.openai.api_key_var();
+        self.openai.api_key = keychain::resolve_api_key(
+            self.openai.api_key.take(),
+            std::env::var(var).ok(),
+            no_keychain,
+        );
+        if self.openai.organization.is_none() {
+            self.openai.organization = std::env::var("OPENAI_ORG_ID").ok();
         }
+        if self.openai.project.is_none() {
+            self.openai.project = std::env::var("OPENAI_PROJECT_ID").ok();
+        }
+
+        self.anthropic.api_key = keychain::resolve_api_key(
+            self.anthropic.api_key.take(),
+            std::env::var("ANTHROPIC_API_KEY").ok(),
+            no_keychain,
+        );
+    }
 
-        // Also check ANTHROPIC_API_KEY for future use
-        if let Ok(_key) = std::env::var("ANTHROPIC_API_KEY") {
-            // Could be used for Anthropic provider in future
+    /// Providers that can be called with this config: Ollama, then OpenAI and Anthropic
+    /// when they have credentials.
+    pub fn callable_providers(&self) -> Vec<&'static str> {
+        let mut names = vec!["ollama"];
+        if self.openai.has_credentials() {
+            names.push("openai");
+        }
+        if self.anthropic.api_key.is_some() {
+            names.push("anthropic");
+        }
+        names
+    }
+
+    /// The keyed cloud providers the selected provider can call: both of them when
+    /// routing is `auto`.
+    pub fn keyed_providers(&self) -> &'static [&'static str] {
+        match self.provider.as_str() {
+            "openai" => &["openai"],
+            "anthropic" => &["anthropic"],
+            "auto" => &["openai", "anthropic"],
+            _ => &[],
+        }
+    }
+
+    /// Fill the keys still missing for `providers` from the OS keychain. Each lookup
+    /// can prompt for an unlock, so only ask for the providers about to be used.
+    pub fn load_keychain_keys(&mut self, providers: &[&str]) {
+        self.load_keychain_keys_with(providers, keychain::get);
+    }
+
+    fn load_keychain_keys_with(
+        &mut self,
+        providers: &[&str],
+        get: impl Fn(&str) -> Option<String>,
+    ) {
+        if providers.contains(&"openai") && self.openai.api_key.is_none() {
+            self.openai.api_key = get(self.openai.api_key_var());
+        }
+        if providers.contains(&"anthropic") && self.anthropic.api_key.is_none() {
+            self.anthropic.api_key = get("ANTHROPIC_API_KEY");
         }
     }
 
+    /// `config.toml` in [`app_dir`]: `journal-ai/config.toml` under `$XDG_CONFIG_HOME`,
+    /// or `~/.config` when it is unset, unless relocated.
     pub fn default_config_path() -> Result<PathBuf> {
-        let home = dirs::home_dir().context("Could not determine home directory")?;
-        Ok(home.join(".config").join("journal-ai").join("config.toml"))
+        Ok(app_dir()?.join("config.toml"))
     }
 
-    pub fn init_interactive() -> Result<Self> {
+    pub async fn init_interactive() -> Result<Self> {
         println!("Welcome to journal-ai configuration!");
         println!();
 
@@ -149,23 +1377,69 @@ impl Config {
         println!("Select default provider:");
         println!("1. Ollama (local, recommended for most users)");
         println!("2. OpenAI (cloud, requires API key)");
+        println!("3. Anthropic (cloud, requires API key)");
 
         let mut choice = String::new();
         std::io::stdin().read_line(&mut choice)?;
 
         let provider = match choice.trim() {
             "2" => "openai",
+            "3" => "anthropic",
             _ => "ollama",
         };
 
-        let mut config = Config::default();
-        config.provider = provider.to_string();
+        let mut config = Config {
+            provider: provider.to_string(),
+            ..Config::default()
+        };
+
+        let stdin = std::io::stdin();
+        match provider {
+            "ollama" => {
+                let choices = match ollama::installed_models(&config.ollama).await {
+                    Ok(models) if models.is_empty() => {
+                        println!("No models installed in Ollama yet.");
+                        models
+                    }
+                    Ok(models) => models,
+                    Err(e) => {
+                        println!("Could not list Ollama models: {:#}", e);
+                        Vec::new()
+                    }
+                };
+                config.ollama.model = prompt_model(
+                    &choices,
+                    &config.ollama.model,
+                    &mut stdin.lock(),
+                    &mut std::io::stdout(),
+                )?;
+            }
+            "openai" => {
+                let choices: Vec<String> = OPENAI_MODELS.iter().map(|m| m.to_string()).collect();
+                config.openai.model = prompt_model(
+                    &choices,
+                    &config.openai.model,
+                    &mut stdin.lock(),
+                    &mut std::io::stdout(),
+                )?;
+            }
+            _ => {}
+        }
 
         if provider == "openai" {
             println!("Enter your OpenAI API key (or set OPENAI_API_KEY env var):");
             let mut key = String::new();
             std::io::stdin().read_line(&mut key)?;
             config.openai.api_key = Some(key.trim().to_string());
+            offer_keychain("OPENAI_API_KEY", key.trim())?;
+        }
+
+        if provider == "anthropic" {
+            println!("Enter your Anthropic API key (or set ANTHROPIC_API_KEY env var):");
+            let mut key = String::new();
+            std::io::stdin().read_line(&mut key)?;
+            config.anthropic.api_key = Some(key.trim().to_string());
+            offer_keychain("ANTHROPIC_API_KEY", key.trim())?;
         }
 
         // Save config
@@ -176,12 +1450,15 @@ impl Config {
 
         // Don't serialize api_key to file for security
         let config_to_save = Config {
-            provider: config.provider.clone(),
-            ollama: config.ollama.clone(),
             openai: OpenAiConfig {
                 api_key: None, // Don't save API key to file
                 ..config.openai.clone()
             },
+            anthropic: AnthropicConfig {
+                api_key: None,
+                ..config.anthropic.clone()
+            },
+            ..config.clone()
         };
 
         let toml_string = toml::to_string_pretty(&config_to_save)?;
@@ -205,6 +1482,25 @@ mod tests {
         assert_eq!(config.provider, "ollama");
         assert_eq!(config.ollama.model, "llama3.2");
         assert_eq!(config.openai.model, "gpt-4o-mini");
+        assert_eq!(config.anthropic.model, "claude-3-5-sonnet-latest");
+        assert_eq!(config.ollama.timeout_secs, 120);
+        assert_eq!(config.openai.timeout_secs, 120);
+        assert_eq!(config.retry.max_retries, 3);
+        assert_eq!(config.retry.base_delay_ms, 500);
+        assert!(config.trailing_newline);
+        assert_eq!(config.line_ending, LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_load_line_ending_from_file() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(b"trailing_newline = false\nline_ending = \"crlf\"\n")
+            .unwrap();
+
+        let config = Config::load(Some(temp_file.path().to_path_buf()), None).unwrap();
+        assert!(!config.trailing_newline);
+        assert_eq!(config.line_ending, LineEnding::Crlf);
     }
 
     #[test]
@@ -224,12 +1520,404 @@ model = "gpt-4"
         let mut temp_file = NamedTempFile::new().unwrap();
         temp_file.write_all(toml_content.as_bytes()).unwrap();
 
-        let config = Config::load(Some(temp_file.path().to_path_buf())).unwrap();
+        let config = Config::load(Some(temp_file.path().to_path_buf()), None).unwrap();
         assert_eq!(config.provider, "openai");
         assert_eq!(config.ollama.model, "mistral");
         assert_eq!(config.openai.model, "gpt-4");
     }
 
+    #[test]
+    fn test_prompt_config_system_prompt() {
+        assert_eq!(PromptConfig::default().system_prompt().unwrap(), None);
+
+        let mut prompt_file = NamedTempFile::new().unwrap();
+        prompt_file.write_all(b"From file\n").unwrap();
+        let mut prompt = PromptConfig {
+            system_file: Some(prompt_file.path().to_path_buf()),
+            ..PromptConfig::default()
+        };
+        assert_eq!(
+            prompt.system_prompt().unwrap().as_deref(),
+            Some("From file")
+        );
+
+        prompt.system = Some("Inline".to_string());
+        assert_eq!(prompt.system_prompt().unwrap().as_deref(), Some("Inline"));
+
+        let missing = PromptConfig {
+            system_file: Some(PathBuf::from("/does/not/exist.txt")),
+            ..PromptConfig::default()
+        };
+        assert!(missing.system_prompt().is_err());
+    }
+
+    #[test]
+    fn test_templates() {
+        let mut config = Config::default();
+        assert!(config.template("meeting").unwrap().contains("## Attendees"));
+
+        config.templates.insert(
+            "standup".to_string(),
+            "Yesterday / Today / Blockers".to_string(),
+        );
+        config
+            .templates
+            .insert("daily".to_string(), "My own daily".to_string());
+        assert_eq!(config.template("daily").unwrap(), "My own daily");
+        assert_eq!(
+            config.template_names(),
+            vec!["daily", "idea", "meeting", "standup"]
+        );
+
+        let err = config.template("weekly").unwrap_err().to_string();
+        assert!(err.contains("Unknown template 'weekly'"));
+        assert!(err.contains("daily, idea, meeting, standup"));
+
+        config.prompt.template = Some("standup".to_string());
+        let options = config.prompt_options().unwrap();
+        assert_eq!(
+            options.template.as_deref(),
+            Some("Yesterday / Today / Blockers")
+        );
+    }
+
+    #[test]
+    fn test_validate_temperature_range() {
+        assert!(validate_temperature(0.0).is_ok());
+        assert!(validate_temperature(2.0).is_ok());
+        assert!(validate_temperature(-0.1).is_err());
+        assert!(validate_temperature(2.5).is_err());
+
+        let mut config = Config::default();
+        assert!(config.validate().is_empty());
+        config.openai.temperature = 3.0;
+        assert_eq!(
+            config.validate(),
+            vec!["Invalid [openai] temperature: Temperature must be between 0.0 and 2.0, got 3"]
+        );
+    }
+
+    #[test]
+    fn test_validate_unknown_provider() {
+        let config = Config {
+            provider: "openia".to_string(),
+            ..Config::default()
+        };
+        let problems = config.validate();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("Unknown provider 'openia'"));
+    }
+
+    #[test]
+    fn test_validate_extension() {
+        let mut config = Config::default();
+        for extension in ["txt", "org", "md"] {
+            config.journal.extension = extension.to_string();
+            assert!(config.validate().is_empty(), "{}", extension);
+            assert_eq!(config.title_options().extension, extension);
+        }
+        for extension in ["", ".txt", "TXT", "t/x"] {
+            config.journal.extension = extension.to_string();
+            assert_eq!(config.validate().len(), 1, "{}", extension);
+        }
+    }
+
+    #[test]
+    fn test_validate_file_journal_version() {
+        let mut config = Config::default();
+        config.journal.file_journal_version = Some(">=0.1.0, <1.0.0".to_string());
+        assert!(config.validate().is_empty());
+        config.journal.file_journal_version = Some("^0.3".to_string());
+        assert_eq!(config.validate().len(), 1);
+    }
+
+    #[test]
+    fn test_validate_malformed_base_url() {
+        let mut config = Config::default();
+        config.ollama.base_url = "localhost:11434".to_string();
+        config.anthropic.base_url = "not a url".to_string();
+        let problems = config.validate();
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].starts_with("Invalid [ollama] base_url 'localhost:11434'"));
+        assert!(problems[1].starts_with("Invalid [anthropic] base_url 'not a url'"));
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let unknown =
+            Config::unknown_keys("provder = \"openai\"\n[ollama]\nmodel = \"x\"\nmodle = \"y\"\n")
+                .unwrap();
+        assert_eq!(unknown, vec!["ollama.modle", "provder"]);
+        assert!(Config::unknown_keys("[templates]\nstandup = \"x\"\n")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_unknown_keys_warning() {
+        let path = Path::new("/etc/journal-ai.toml");
+        let warning = unknown_keys_warning(
+            path,
+            "provider = \"ollama\"\ntempreature = 0.2\n[olama]\nmodel = \"x\"\n[openai]\nmodle = \"y\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            warning,
+            "Ignoring unknown config keys in /etc/journal-ai.toml: olama, openai.modle, tempreature"
+        );
+        assert!(unknown_keys_warning(path, "provider = \"openai\"\n").is_none());
+        assert!(unknown_keys_warning(path, "provider = ").is_none());
+    }
+
+    const PROFILES: &str = r#"
+provider = "ollama"
+
+[ollama]
+model = "llama3.2"
+temperature = 0.3
+
+[profiles.work]
+provider = "openai"
+
+[profiles.work.ollama]
+model = "mistral"
+
+[profiles.work.prompt]
+system = "Formal tone."
+"#;
+
+    #[test]
+    fn test_profile_overrides_base() {
+        let config = Config::parse(PROFILES, Some("work")).unwrap();
+        assert_eq!(config.provider, "openai");
+        assert_eq!(config.ollama.model, "mistral");
+        assert_eq!(config.prompt.system.as_deref(), Some("Formal tone."));
+        // Keys the profile leaves out come from the base config
+        assert_eq!(config.ollama.temperature, Some(0.3));
+        assert_eq!(config.openai.model, "gpt-4o-mini");
+    }
+
+    #[test]
+    fn test_no_profile_uses_base() {
+        let config = Config::parse(PROFILES, None).unwrap();
+        assert_eq!(config.provider, "ollama");
+        assert_eq!(config.ollama.model, "llama3.2");
+        assert_eq!(config.prompt.system, None);
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let err = Config::parse(PROFILES, Some("home")).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown profile 'home'. Available: work");
+        assert!(Config::parse("", Some("work")).is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_in_profile() {
+        let unknown = Config::unknown_keys("[profiles.work]\nprovder = \"openai\"\n").unwrap();
+        assert_eq!(unknown, vec!["profiles.work.provder"]);
+        assert!(Config::unknown_keys(PROFILES).unwrap().is_empty());
+    }
+
+    fn lookup(name: &str) -> Option<String> {
+        (name == "OLLAMA_HOST").then(|| "http://gpu-box:11434".to_string())
+    }
+
+    #[test]
+    fn test_expand_env_vars_set() {
+        let mut value: toml::Value =
+            toml::from_str("[ollama]\nbase_url = \"${OLLAMA_HOST}/\"\n").unwrap();
+        expand_env_in_value(&mut value, "", &lookup).unwrap();
+        assert_eq!(
+            value["ollama"]["base_url"].as_str(),
+            Some("http://gpu-box:11434/")
+        );
+    }
+
+    #[test]
+    fn test_expand_env_vars_unset() {
+        let mut value: toml::Value =
+            toml::from_str("[openai]\nmodel = \"${OPENAI_MODEL}\"\n").unwrap();
+        let err = expand_env_in_value(&mut value, "", &lookup).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Environment variable 'OPENAI_MODEL' used in config field 'openai.model' is not set"
+        );
+    }
+
+    #[test]
+    fn test_expand_env_vars_literal() {
+        assert_eq!(
+            expand_env_vars("{title} costs $5", "journal.new_args[1]", &lookup).unwrap(),
+            "{title} costs $5"
+        );
+    }
+
+    #[test]
+    fn test_expand_env_vars_escaped() {
+        assert_eq!(
+            expand_env_vars("$${TITLE} at ${OLLAMA_HOST}", "prompt", &lookup).unwrap(),
+            "${TITLE} at http://gpu-box:11434"
+        );
+        assert_eq!(
+            expand_env_vars("$${unclosed", "prompt", &lookup).unwrap(),
+            "${unclosed"
+        );
+    }
+
+    fn pick(choices: &[&str], answer: &str) -> (String, String) {
+        let choices: Vec<String> = choices.iter().map(|c| c.to_string()).collect();
+        let mut out = Vec::new();
+        let model = prompt_model(&choices, "llama3.2", &mut answer.as_bytes(), &mut out).unwrap();
+        (model, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_prompt_model_by_number() {
+        let (model, shown) = pick(&["gemma2:2b", "mistral:latest"], "2\n");
+        assert_eq!(model, "mistral:latest");
+        assert!(shown.contains("1. gemma2:2b\n2. mistral:latest\n"));
+    }
+
+    #[test]
+    fn test_prompt_model_default_and_free_text() {
+        assert_eq!(pick(&["gemma2:2b"], "\n").0, "llama3.2");
+        assert_eq!(pick(&["gemma2:2b"], "7\n").0, "llama3.2");
+        assert_eq!(pick(&["gemma2:2b"], "qwen2.5:7b\n").0, "qwen2.5:7b");
+        // Without a list only a name is asked for
+        let (model, shown) = pick(&[], "phi3\n");
+        assert_eq!(model, "phi3");
+        assert_eq!(shown, "Model name [llama3.2]: ");
+    }
+
+    #[test]
+    fn test_config_dir_honors_xdg() {
+        let home = Path::new("/home/me");
+        assert_eq!(
+            config_dir(Some("/srv/config".into()), home),
+            PathBuf::from("/srv/config")
+        );
+        assert_eq!(config_dir(None, home), PathBuf::from("/home/me/.config"));
+        assert_eq!(
+            config_dir(Some("".into()), home),
+            PathBuf::from("/home/me/.config")
+        );
+        assert_eq!(
+            config_dir(Some("relative/dir".into()), home),
+            PathBuf::from("/home/me/.config")
+        );
+    }
+
+    /// Serializes tests that touch the process-wide config location.
+    static APP_DIR_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn test_app_dir_precedence() {
+        let home = Path::new("/home/me");
+        let resolve = |flag: Option<&str>, env: Option<&str>| {
+            resolve_app_dir(flag.map(PathBuf::from), env.map(Into::into), None, home)
+        };
+        assert_eq!(
+            resolve(Some("/srv/flag"), Some("/srv/env")),
+            PathBuf::from("/srv/flag")
+        );
+        assert_eq!(resolve(None, Some("/srv/env")), PathBuf::from("/srv/env"));
+        assert_eq!(resolve(None, Some("")), home.join(".config/journal-ai"));
+        assert_eq!(resolve(None, None), home.join(".config/journal-ai"));
+    }
+
+    #[test]
+    fn test_config_dir_relocates_config_and_cache() {
+        let _lock = APP_DIR_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = tempfile::tempdir().unwrap();
+        set_app_dir(Some(dir.path().to_path_buf()));
+        let config_path = Config::default_config_path();
+        let cache_dir = crate::cache::Cache::default_dir();
+        set_app_dir(None);
+
+        assert_eq!(config_path.unwrap(), dir.path().join("config.toml"));
+        assert_eq!(cache_dir.unwrap(), dir.path().join("cache"));
+    }
+
+    #[test]
+    fn test_default_config_path_uses_xdg_config_home() {
+        let _lock = APP_DIR_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var("XDG_CONFIG_HOME", dir.path());
+        let path = Config::default_config_path();
+        std::env::remove_var("XDG_CONFIG_HOME");
+
+        assert_eq!(
+            path.unwrap(),
+            dir.path().join("journal-ai").join("config.toml")
+        );
+    }
+
+    #[test]
+    fn test_env_overrides_provider_and_model() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        let mut config = Config::parse("provider = \"ollama\"\n", None).unwrap();
+        config.apply_env_overrides(env(&[
+            ("JOURNAL_AI_PROVIDER", "openai"),
+            ("JOURNAL_AI_MODEL", "gpt-4o"),
+        ]));
+        assert_eq!(config.provider, "openai");
+        assert_eq!(config.openai.model, "gpt-4o");
+        assert_eq!(config.ollama.model, OllamaConfig::default().model);
+
+        // The model alone goes to the provider from the file
+        let mut config = Config::parse("provider = \"anthropic\"\n", None).unwrap();
+        config.apply_env_overrides(env(&[("JOURNAL_AI_MODEL", "claude-3-5-haiku-latest")]));
+        assert_eq!(config.provider, "anthropic");
+        assert_eq!(config.anthropic.model, "claude-3-5-haiku-latest");
+
+        // Unset or empty variables change nothing
+        let mut config = Config::default();
+        config.apply_env_overrides(env(&[("JOURNAL_AI_PROVIDER", "")]));
+        assert_eq!(config.provider, Config::default().provider);
+    }
+
+    #[test]
+    fn test_redacted_config_masks_keys() {
+        let mut config = Config::parse(
+            r#"
+            provider = "openai"
+            [openai]
+            model = "gpt-4o"
+            "#,
+            None,
+        )
+        .unwrap();
+        config.openai.api_key = Some("sk-test-1234567890abcd".to_string());
+        config.anthropic.api_key = Some("short".to_string());
+
+        let toml = config.to_redacted_toml().unwrap();
+        assert!(toml.contains("provider = \"openai\""));
+        assert!(toml.contains("model = \"gpt-4o\""));
+        assert!(toml.contains("temperature = 0.1\n"));
+        assert!(toml.contains("api_key = \"****abcd\""));
+        assert!(toml.contains("api_key = \"****\""));
+        assert!(!toml.contains("sk-test"));
+
+        let json = config.to_redacted_json().unwrap();
+        assert_eq!(json["provider"], "openai");
+        assert_eq!(json["openai"]["temperature"], 0.1);
+        assert_eq!(json["openai"]["model"], "gpt-4o");
+        assert_eq!(json["openai"]["api_key"], "****abcd");
+        assert!(!json.to_string().contains("sk-test"));
+
+        config.openai.api_key = None;
+        let json = config.to_redacted_json().unwrap();
+        assert!(json["openai"].get("api_key").is_none());
+    }
+
     #[test]
     fn test_load_api_key_from_env() {
         std::env::set_var("OPENAI_API_KEY", "test-key-123");
@@ -241,4 +1929,47 @@ model = "gpt-4"
 
         std::env::remove_var("OPENAI_API_KEY");
     }
+
+    #[test]
+    fn test_callable_providers_need_credentials() {
+        let mut config = Config::default();
+        config.openai.api_key = None;
+        config.anthropic.api_key = None;
+        assert_eq!(config.callable_providers(), vec!["ollama"]);
+
+        config.anthropic.api_key = Some("sk-ant-test".to_string());
+        assert_eq!(config.callable_providers(), vec!["ollama", "anthropic"]);
+    }
+
+    #[test]
+    fn test_keychain_only_queried_for_providers_in_use() {
+        let asked = std::cell::RefCell::new(Vec::new());
+        let get = |name: &str| {
+            asked.borrow_mut().push(name.to_string());
+            Some(format!("{}-key", name))
+        };
+
+        let mut config = Config {
+            provider: "anthropic".to_string(),
+            ..Config::default()
+        };
+        config.load_keychain_keys_with(config.keyed_providers(), get);
+        assert_eq!(*asked.borrow(), vec!["ANTHROPIC_API_KEY"]);
+        assert!(config.openai.api_key.is_none());
+
+        asked.borrow_mut().clear();
+        let mut config = Config {
+            provider: "auto".to_string(),
+            ..Config::default()
+        };
+        config.anthropic.api_key = Some("from-file".to_string());
+        config.load_keychain_keys_with(config.keyed_providers(), get);
+        assert_eq!(*asked.borrow(), vec!["OPENAI_API_KEY"]);
+        assert_eq!(config.anthropic.api_key.as_deref(), Some("from-file"));
+
+        asked.borrow_mut().clear();
+        let mut config = Config::default();
+        config.load_keychain_keys_with(config.keyed_providers(), get);
+        assert!(asked.borrow().is_empty());
+    }
 }
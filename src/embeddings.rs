@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::journal;
+
+/// How many recent entries the index keeps; older ones are dropped on save.
+const MAX_INDEXED: usize = 500;
+
+/// Turns text into an embedding vector.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// Embedding model, stored with each vector so only comparable ones are compared
+    fn embedding_model(&self) -> &str;
+}
+
+/// Cosine similarity of two vectors; 0.0 when they differ in length or one is all zeros.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Whether `similarity` means the entry repeats an earlier one.
+pub fn is_duplicate(similarity: f32, threshold: f32) -> bool {
+    similarity >= threshold
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedEntry {
+    pub title: String,
+    pub model: String,
+    pub embedding: Vec<f32>,
+}
+
+/// Embeddings of recently saved entries, stored as one JSON file.
+pub struct EmbeddingIndex {
+    path: PathBuf,
+    entries: Vec<IndexedEntry>,
+}
+
+impl EmbeddingIndex {
+    /// `embeddings.json` in the config directory.
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(config::app_dir()?.join("embeddings.json"))
+    }
+
+    /// The index at `path`; a missing or unreadable file starts an empty one.
+    pub fn load(path: PathBuf) -> Self {
+        let entries = read_entries(&path);
+        Self { path, entries }
+    }
+
+    /// The indexed entry closest to `embedding` among those from the same model.
+    pub fn most_similar(&self, model: &str, embedding: &[f32]) -> Option<(&IndexedEntry, f32)> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.model == model)
+            .map(|entry| (entry, cosine_similarity(&entry.embedding, embedding)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Add an entry and write the index, keeping the most recent `MAX_INDEXED`. The file
+    /// is re-read under a lock first, so entries other jobs added since `load` are kept.
+    pub fn add(&mut self, entry: IndexedEntry) -> Result<()> {
+        journal::with_file_lock(&self.path, || {
+            self.entries = read_entries(&self.path);
+            self.entries.push(entry);
+            let excess = self.entries.len().saturating_sub(MAX_INDEXED);
+            self.entries.drain(..excess);
+            let content = serde_json::to_string(&self.entries)?;
+            journal::write_atomic(&self.path, &content)
+                .with_context(|| format!("Failed to write embedding index {}", self.path.display()))
+        })
+    }
+}
+
+fn read_entries(path: &Path) -> Vec<IndexedEntry> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 2.0], &[1.0, 2.0, 3.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn test_is_duplicate_threshold() {
+        assert!(is_duplicate(0.95, 0.92));
+        assert!(is_duplicate(0.92, 0.92));
+        assert!(!is_duplicate(0.91, 0.92));
+    }
+
+    #[test]
+    fn test_index_round_trip_and_model_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index").join("embeddings.json");
+        let mut index = EmbeddingIndex::load(path.clone());
+        assert!(index.most_similar("m", &[1.0, 0.0]).is_none());
+
+        for (title, model, embedding) in [
+            ("call-jan.md", "m", vec![1.0, 0.1]),
+            ("gym.md", "m", vec![0.0, 1.0]),
+            ("other-model.md", "n", vec![1.0, 0.0]),
+        ] {
+            index
+                .add(IndexedEntry {
+                    title: title.to_string(),
+                    model: model.to_string(),
+                    embedding,
+                })
+                .unwrap();
+        }
+
+        let index = EmbeddingIndex::load(path);
+        let (entry, similarity) = index.most_similar("m", &[1.0, 0.0]).unwrap();
+        assert_eq!(entry.title, "call-jan.md");
+        assert!(is_duplicate(similarity, 0.92));
+        let (entry, _) = index.most_similar("n", &[0.0, 1.0]).unwrap();
+        assert_eq!(entry.title, "other-model.md");
+    }
+
+    #[test]
+    fn test_add_keeps_entries_added_since_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("embeddings.json");
+        let entry = |title: &str| IndexedEntry {
+            title: title.to_string(),
+            model: "m".to_string(),
+            embedding: vec![1.0, 0.0],
+        };
+        // Two jobs load the index before either has saved
+        let mut first = EmbeddingIndex::load(path.clone());
+        let mut second = EmbeddingIndex::load(path.clone());
+        first.add(entry("first.md")).unwrap();
+        second.add(entry("second.md")).unwrap();
+
+        let titles: Vec<String> = EmbeddingIndex::load(path)
+            .entries
+            .into_iter()
+            .map(|entry| entry.title)
+            .collect();
+        assert_eq!(titles, vec!["first.md", "second.md"]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+/// Help text listing the exit codes, shown by `--help`.
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  success
+  1  other failure
+  2  invalid or unreadable configuration
+  3  provider unavailable (not running, unreachable, or missing API key)
+  4  entry generation failed
+  5  file-journal failed
+  130  interrupted (Ctrl-C)";
+
+/// Exit code after Ctrl-C, following the shell convention of 128 + SIGINT.
+pub const INTERRUPTED: i32 = 130;
+
+/// A failure category that decides the process exit code.
+///
+/// Wraps the underlying error without changing how it prints: `Display` shows the
+/// wrapped message and `source` continues with its causes.
+#[derive(Debug)]
+pub enum Failure {
+    Config(anyhow::Error),
+    ProviderUnavailable(anyhow::Error),
+    Generation(anyhow::Error),
+    Journal(anyhow::Error),
+}
+
+impl Failure {
+    /// A generation error, or `ProviderUnavailable` when the provider could not be reached.
+    pub fn generation(err: anyhow::Error) -> Self {
+        let unreachable = err
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+            .any(|e| e.is_connect());
+        if unreachable {
+            Failure::ProviderUnavailable(err)
+        } else {
+            Failure::Generation(err)
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        match self {
+            Failure::Config(_) => 2,
+            Failure::ProviderUnavailable(_) => 3,
+            Failure::Generation(_) => 4,
+            Failure::Journal(_) => 5,
+        }
+    }
+
+    fn inner(&self) -> &anyhow::Error {
+        match self {
+            Failure::Config(e)
+            | Failure::ProviderUnavailable(e)
+            | Failure::Generation(e)
+            | Failure::Journal(e) => e,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner())
+    }
+}
+
+impl std::error::Error for Failure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.inner().source()
+    }
+}
+
+/// Exit code for an error: the category found in its chain, else 1.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<Failure>())
+        .map_or(1, Failure::code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn test_failure_codes() {
+        let cases = [
+            (Failure::Config(anyhow!("bad toml")), 2),
+            (Failure::ProviderUnavailable(anyhow!("no key")), 3),
+            (Failure::Generation(anyhow!("bad json")), 4),
+            (Failure::Journal(anyhow!("not installed")), 5),
+        ];
+        for (failure, code) in cases {
+            assert_eq!(exit_code(&failure.into()), code);
+        }
+    }
+
+    #[test]
+    fn test_uncategorized_error_exits_1() {
+        assert_eq!(exit_code(&anyhow!("something else")), 1);
+    }
+
+    #[test]
+    fn test_code_survives_added_context() {
+        let err = anyhow::Error::from(Failure::Journal(anyhow!("not installed")))
+            .context("Failed to save entry");
+        assert_eq!(exit_code(&err), 5);
+    }
+
+    #[test]
+    fn test_failure_prints_like_the_wrapped_error() {
+        let inner = Err::<(), _>(anyhow!("connection refused"))
+            .context("Failed to generate entry using ollama")
+            .unwrap_err();
+        let expected = format!("{:#}", inner);
+        let err = anyhow::Error::from(Failure::Generation(inner));
+        assert_eq!(format!("{:#}", err), expected);
+        assert_eq!(err.to_string(), "Failed to generate entry using ollama");
+    }
+}
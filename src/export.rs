@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedEntry {
+    path: String,
+    content: String,
+}
+
+fn read_entries(journal_root: &Path, entries: &[PathBuf]) -> Result<Vec<ExportedEntry>> {
+    entries
+        .iter()
+        .map(|path| {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read entry {}", path.display()))?;
+            let rel = path.strip_prefix(journal_root).unwrap_or(path);
+            Ok(ExportedEntry {
+                path: rel.to_string_lossy().to_string(),
+                content,
+            })
+        })
+        .collect()
+}
+
+/// Render the given entries as a single document.
+///
+/// Markdown output keeps each entry verbatim (frontmatter included) and marks the
+/// start of every entry with an HTML comment carrying its relative path.
+pub fn render(journal_root: &Path, entries: &[PathBuf], format: ExportFormat) -> Result<String> {
+    let entries = read_entries(journal_root, entries)?;
+
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(&entries).context("Failed to serialize entries")
+        }
+        ExportFormat::Markdown => {
+            let mut out = String::new();
+            for (i, entry) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push('\n');
+                }
+                out.push_str(&format!("<!-- entry: {} -->\n", entry.path));
+                out.push_str(entry.content.trim_end());
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (tempfile::TempDir, Vec<PathBuf>) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("2026/02")).unwrap();
+        let a = root.join("2026/02/01-080000-a.md");
+        let b = root.join("2026/02/02-080000-b.md");
+        fs::write(&a, "---\ntags: [x]\n---\n\nFirst\n").unwrap();
+        fs::write(&b, "Second").unwrap();
+        (dir, vec![a, b])
+    }
+
+    #[test]
+    fn test_render_markdown_keeps_frontmatter_and_separates_entries() {
+        let (dir, entries) = setup();
+        let out = render(dir.path(), &entries, ExportFormat::Markdown).unwrap();
+        assert_eq!(
+            out,
+            "<!-- entry: 2026/02/01-080000-a.md -->\n---\ntags: [x]\n---\n\nFirst\n\n<!-- entry: 2026/02/02-080000-b.md -->\nSecond\n"
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let (dir, entries) = setup();
+        let out = render(dir.path(), &entries, ExportFormat::Json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(parsed[1]["path"], "2026/02/02-080000-b.md");
+        assert_eq!(parsed[1]["content"], "Second");
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::journal;
+
+/// Sections of `text` separated by lines that are exactly `delimiter` (surrounding
+/// whitespace ignored). Sections are trimmed and empty ones dropped.
+pub fn split_sections(text: &str, delimiter: &str) -> Vec<String> {
+    let mut sections = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        if line.trim() == delimiter {
+            sections.push(std::mem::take(&mut current));
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    sections.push(current);
+    sections
+        .into_iter()
+        .map(|section| section.trim().to_string())
+        .filter(|section| !section.is_empty())
+        .collect()
+}
+
+/// Fingerprint of a section (64-bit FNV-1a). Unlike `DefaultHasher` it stays the same
+/// across builds, so upgrading never re-imports old sections.
+pub fn section_hash(section: &str) -> String {
+    let hash = section
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
+/// Which sections of each imported file were already turned into entries.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportState {
+    /// Section hashes by canonical file path
+    files: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ImportState {
+    /// `import-state.json` in the config directory.
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(config::app_dir()?.join("import-state.json"))
+    }
+
+    /// The state at `path`; a missing file means nothing was imported yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read import state {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse import state {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        journal::write_atomic(path, &serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write import state {}", path.display()))
+    }
+
+    /// The sections of `file` not imported yet, in order.
+    pub fn pending(&self, file: &str, sections: Vec<String>) -> Vec<String> {
+        let imported = self.files.get(file);
+        sections
+            .into_iter()
+            .filter(|section| {
+                imported.is_none_or(|hashes| !hashes.contains(&section_hash(section)))
+            })
+            .collect()
+    }
+
+    pub fn mark_imported(&mut self, file: &str, section: &str) {
+        self.files
+            .entry(file.to_string())
+            .or_default()
+            .insert(section_hash(section));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sections() {
+        let text = "Call Jan\nabout Q2\n---\n\n  ---  \nGym at 7\n---\nIdea: --- is not a delimiter here\n";
+        assert_eq!(
+            split_sections(text, "---"),
+            [
+                "Call Jan\nabout Q2",
+                "Gym at 7",
+                "Idea: --- is not a delimiter here"
+            ]
+        );
+        assert_eq!(split_sections("one\n%%\ntwo", "%%"), ["one", "two"]);
+        assert!(split_sections("\n---\n", "---").is_empty());
+    }
+
+    #[test]
+    fn test_section_hash_is_stable() {
+        assert_eq!(section_hash(""), "cbf29ce484222325");
+        assert_eq!(section_hash("a"), "af63dc4c8601ec8c");
+        assert_ne!(section_hash("Call Jan"), section_hash("Call Jan."));
+    }
+
+    #[test]
+    fn test_pending_skips_imported_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("import-state.json");
+        let sections = || vec!["Call Jan".to_string(), "Gym at 7".to_string()];
+
+        let mut state = ImportState::load(&path).unwrap();
+        assert_eq!(state.pending("/notes/scratch.md", sections()), sections());
+        state.mark_imported("/notes/scratch.md", "Call Jan");
+        state.save(&path).unwrap();
+
+        let state = ImportState::load(&path).unwrap();
+        assert_eq!(state.pending("/notes/scratch.md", sections()), ["Gym at 7"]);
+        // Another file with the same text is tracked separately
+        assert_eq!(state.pending("/notes/other.md", sections()), sections());
+    }
+}
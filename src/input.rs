@@ -0,0 +1,744 @@
+use anyhow::{anyhow, Context, Result};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::config::validate_url;
+use crate::format_preview;
+use crate::providers::{self, LlmResponse};
+
+/// A positional argument: a file whose contents become an entry, or the note text itself.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Input {
+    File(PathBuf),
+    Text(String),
+}
+
+impl Input {
+    /// An existing file is read; anything else is taken literally.
+    pub fn classify(arg: &str) -> Self {
+        let path = Path::new(arg);
+        if path.is_file() {
+            Input::File(path.to_path_buf())
+        } else {
+            Input::Text(arg.to_string())
+        }
+    }
+
+    /// The positional arguments as inputs. Only when at least one is an existing file
+    /// does each argument become its own entry; otherwise the words are one note, as
+    /// when it was typed without quotes.
+    pub fn classify_all(args: &[String]) -> Vec<Self> {
+        if args.iter().any(|arg| Path::new(arg).is_file()) {
+            args.iter().map(|arg| Self::classify(arg)).collect()
+        } else if args.is_empty() {
+            vec![]
+        } else {
+            vec![Input::Text(args.join(" "))]
+        }
+    }
+
+    /// Short name for progress and summary lines.
+    pub fn label(&self) -> String {
+        match self {
+            Input::File(path) => path.display().to_string(),
+            Input::Text(text) => {
+                let mut label: String = text.chars().take(30).collect();
+                if text.chars().count() > 30 {
+                    label.push_str("...");
+                }
+                format!("\"{}\"", label)
+            }
+        }
+    }
+
+    pub fn read(&self) -> Result<String> {
+        match self {
+            Input::File(path) => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                if content.trim().is_empty() {
+                    return Err(anyhow!("{} is empty", path.display()));
+                }
+                Ok(content.trim().to_string())
+            }
+            Input::Text(text) => Ok(text.clone()),
+        }
+    }
+}
+
+/// Where the note text comes from.
+#[derive(Debug, PartialEq, Eq)]
+enum Source {
+    InputFile,
+    Arg,
+    Editor,
+    Clipboard,
+    Stdin,
+}
+
+/// Pick the content source: `--input-file` wins, then the argument, then piped stdin;
+/// `--edit` and `--clipboard` only apply when stdin is a terminal.
+fn content_source(
+    has_input_file: bool,
+    has_arg: bool,
+    stdin_is_terminal: bool,
+    edit: bool,
+    clipboard: bool,
+) -> Source {
+    if has_input_file {
+        Source::InputFile
+    } else if has_arg {
+        Source::Arg
+    } else if edit && stdin_is_terminal {
+        Source::Editor
+    } else if clipboard && stdin_is_terminal {
+        Source::Clipboard
+    } else {
+        Source::Stdin
+    }
+}
+
+/// The note in `--input-file`, which must exist and not be empty.
+fn read_input_file(path: &Path) -> Result<String> {
+    if !path.is_file() {
+        return Err(anyhow!("Input file {} does not exist", path.display()));
+    }
+    Input::File(path.to_path_buf()).read()
+}
+
+fn no_content_error() -> anyhow::Error {
+    anyhow!(
+        "No content provided. Use positional argument or pipe content via stdin.\n\
+         Example: journal-ai 'My note here'\n\
+         Or: echo 'My note' | journal-ai"
+    )
+}
+
+/// Resolve the note text from `--input-file`, the positional argument, `$EDITOR`, the
+/// clipboard, or stdin.
+///
+/// The editor and clipboard are only used when no content was given and stdin is a
+/// terminal; piped input always wins.
+pub fn read_content(
+    input_file: Option<&Path>,
+    content: Option<String>,
+    edit: bool,
+    clipboard: bool,
+) -> Result<String> {
+    let text = match content_source(
+        input_file.is_some(),
+        content.is_some(),
+        io::stdin().is_terminal(),
+        edit,
+        clipboard,
+    ) {
+        Source::InputFile => return read_input_file(input_file.unwrap_or(Path::new(""))),
+        Source::Arg => return Ok(content.unwrap_or_default()),
+        Source::Editor => return edit_in_editor(),
+        Source::Clipboard => read_clipboard()?,
+        Source::Stdin => read_lossy(&mut io::stdin().lock())?,
+    };
+    if text.trim().is_empty() {
+        return Err(no_content_error());
+    }
+    Ok(text.trim().to_string())
+}
+
+/// Fetch the web page at `url` and return its readable text, giving up after `timeout`.
+/// Anything but an HTML page is an error.
+pub async fn read_url(url: &str, timeout: Duration, proxy: Option<&str>) -> Result<String> {
+    validate_url(url).with_context(|| format!("Invalid URL '{}'", url))?;
+    let client = providers::build_client(timeout, proxy)?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .with_context(|| format!("Failed to fetch {}", url))?;
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    if !matches!(mime.as_str(), "text/html" | "application/xhtml+xml") {
+        let shown = if mime.is_empty() {
+            "no content type"
+        } else {
+            mime.as_str()
+        };
+        return Err(anyhow!(
+            "{} is not an HTML page ({}); save it and use --input-file instead",
+            url,
+            shown
+        ));
+    }
+
+    let html = read_capped(response, MAX_PAGE_BYTES)
+        .await
+        .with_context(|| format!("Failed to read {}", url))?;
+    let text = html_to_text(&html);
+    if text.is_empty() {
+        return Err(anyhow!("{} has no readable text", url));
+    }
+    Ok(text)
+}
+
+/// Largest page `read_url` downloads; anything bigger is not an article.
+const MAX_PAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// The response body as text, failing once it grows past `limit` bytes rather than
+/// holding all of it in memory.
+async fn read_capped(mut response: reqwest::Response, limit: usize) -> Result<String> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > limit as u64)
+    {
+        return Err(anyhow!("the page is larger than {} KiB", limit / 1024));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(anyhow!("the page is larger than {} KiB", limit / 1024));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Elements whose contents are never the page's text: code, styling and page chrome.
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "head", "nav", "header", "footer", "aside",
+    "form",
+];
+
+/// Elements that start a paragraph of their own.
+const PARAGRAPH_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "table",
+    "section",
+    "article",
+    "main",
+    "blockquote",
+    "pre",
+    "hr",
+    "figure",
+];
+
+/// Elements that start a new line.
+const LINE_ELEMENTS: &[&str] = &["br", "li", "tr", "dt", "dd", "figcaption"];
+
+/// The readable text of an HTML document: tags dropped, entities decoded, block elements
+/// turned into lines and paragraphs, and scripts, styles and navigation left out. A
+/// `<header>` inside an `<article>` holds the article's own title, so it is kept.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    let mut articles = 0usize;
+    while let Some(start) = rest.find('<') {
+        push_text(&mut text, &rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if name == "article" {
+            articles = if closing {
+                articles.saturating_sub(1)
+            } else {
+                articles + 1
+            };
+        }
+        let kept_header = name == "header" && articles > 0;
+
+        if !closing
+            && !tag.ends_with('/')
+            && !kept_header
+            && SKIPPED_ELEMENTS.contains(&name.as_str())
+        {
+            // Jump straight to the closing tag: scripts may contain '<' and '>'
+            let close = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&close) {
+                Some(at) => rest[at..].find('>').map_or("", |end| &rest[at + end + 1..]),
+                None => "",
+            };
+        } else if kept_header || PARAGRAPH_ELEMENTS.contains(&name.as_str()) {
+            text.push_str("\n\n");
+        } else if !closing && LINE_ELEMENTS.contains(&name.as_str()) {
+            text.push('\n');
+            if name == "li" {
+                text.push_str("- ");
+            }
+        }
+    }
+    push_text(&mut text, rest);
+
+    tidy_lines(&text)
+}
+
+/// Append a run of text between tags. Source line breaks are only layout in HTML.
+fn push_text(text: &mut String, raw: &str) {
+    text.push_str(&decode_entities(&raw.replace(['\n', '\r', '\t'], " ")));
+}
+
+/// Collapse spaces within lines and blank lines between them to one, and trim the ends.
+fn tidy_lines(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank = true;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        out.push_str(&line);
+        blank = false;
+    }
+    out
+}
+
+/// Decode character references like `&amp;` and `&#8217;`; unknown ones are kept as-is.
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..=end]).map(|c| (c, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            return char::from_u32(code);
+        }
+    };
+    Some(c)
+}
+
+/// Read everything from `reader` as text. Invalid UTF-8 is replaced with U+FFFD, with a
+/// warning, rather than failing the whole note over a stray byte.
+fn read_lossy(reader: &mut impl Read) -> Result<String> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .context("Failed to read stdin")?;
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(e) => {
+            eprintln!("Warning: input is not valid UTF-8; invalid bytes were replaced");
+            Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        }
+    }
+}
+
+#[cfg(feature = "clipboard")]
+fn read_clipboard() -> Result<String> {
+    let mut clipboard = arboard::Clipboard::new().context("Failed to open the clipboard")?;
+    match clipboard.get_text() {
+        Ok(text) => Ok(text),
+        // Nothing (or no text) copied reads as an empty note
+        Err(arboard::Error::ContentNotAvailable) => Ok(String::new()),
+        Err(e) => Err(anyhow!("Failed to read the clipboard: {}", e)),
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn read_clipboard() -> Result<String> {
+    Err(anyhow!(
+        "Clipboard support is not built in. Rebuild with `--features clipboard`"
+    ))
+}
+
+/// Show the entry and ask whether to save it; an empty answer means yes.
+pub fn confirm_save(
+    response: &LlmResponse,
+    reader: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<bool> {
+    write!(out, "{}", format_preview(response))?;
+    write!(out, "\nSave this entry? [Y/n] ")?;
+    out.flush()?;
+
+    let mut answer = String::new();
+    if reader.read_line(&mut answer)? == 0 {
+        // EOF (Ctrl-D) cancels
+        return Ok(false);
+    }
+    Ok(matches!(
+        answer.trim().to_lowercase().as_str(),
+        "" | "y" | "yes"
+    ))
+}
+
+/// Whether to ask before saving: only when a person is at the terminal.
+pub fn should_confirm(yes: bool) -> bool {
+    !yes && io::stdin().is_terminal()
+}
+
+/// The user's editor command split into program and arguments (e.g. "code --wait").
+fn editor_command() -> Vec<String> {
+    let fallback = if cfg!(windows) { "notepad" } else { "vi" };
+    let editor = std::env::var("EDITOR")
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| fallback.to_string());
+    editor.split_whitespace().map(str::to_string).collect()
+}
+
+/// Open `$EDITOR` on a scratch file and return what the user saved.
+fn edit_in_editor() -> Result<String> {
+    let file = tempfile::Builder::new()
+        .prefix("journal-ai-")
+        .suffix(".md")
+        .tempfile()
+        .context("Failed to create scratch file for editor")?;
+
+    let command = editor_command();
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("No editor configured. Set $EDITOR"))?;
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(file.path())
+        .status()
+        .with_context(|| format!("Failed to launch editor '{}'", program))?;
+
+    if !status.success() {
+        return Err(anyhow!("Editor exited with {}, aborting", status));
+    }
+
+    let content = std::fs::read_to_string(file.path())
+        .with_context(|| format!("Failed to read {}", file.path().display()))?;
+    if content.trim().is_empty() {
+        return Err(anyhow!("Editor returned an empty note, aborting"));
+    }
+
+    Ok(content.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_existing_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "  Rough note\n").unwrap();
+        let arg = file.path().to_str().unwrap();
+
+        let input = Input::classify(arg);
+        assert_eq!(input, Input::File(file.path().to_path_buf()));
+        assert_eq!(input.read().unwrap(), "Rough note");
+    }
+
+    #[test]
+    fn test_classify_literal_text() {
+        assert_eq!(
+            Input::classify("Call Jan about Q2"),
+            Input::Text("Call Jan about Q2".to_string())
+        );
+        assert_eq!(
+            Input::classify("notes/missing.txt"),
+            Input::Text("notes/missing.txt".to_string())
+        );
+        // Directories are not readable notes
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            Input::classify(dir.path().to_str().unwrap()),
+            Input::Text(_)
+        ));
+    }
+
+    #[test]
+    fn test_classify_all_splits_only_with_files() {
+        let words = ["Call", "Jan", "about", "Q2"].map(String::from);
+        assert_eq!(
+            Input::classify_all(&words),
+            vec![Input::Text("Call Jan about Q2".to_string())]
+        );
+        assert!(Input::classify_all(&[]).is_empty());
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let args = [
+            file.path().to_str().unwrap().to_string(),
+            "Call Jan".to_string(),
+        ];
+        assert_eq!(
+            Input::classify_all(&args),
+            vec![
+                Input::File(file.path().to_path_buf()),
+                Input::Text("Call Jan".to_string())
+            ]
+        );
+    }
+
+    fn response() -> LlmResponse {
+        LlmResponse {
+            title: "call-jan.md".to_string(),
+            content: "Call Jan".to_string(),
+            tags: vec!["work".to_string()],
+            tasks: vec![],
+            model: None,
+            usage: None,
+        }
+    }
+
+    fn confirm(answer: &str) -> (bool, String) {
+        let mut out = Vec::new();
+        let confirmed = confirm_save(&response(), &mut answer.as_bytes(), &mut out).unwrap();
+        (confirmed, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_confirm_save_yes_and_default() {
+        for answer in ["y\n", "YES\n", "\n"] {
+            assert!(confirm(answer).0, "{:?}", answer);
+        }
+    }
+
+    #[test]
+    fn test_confirm_save_no() {
+        for answer in ["n\n", "No\n", "whatever\n", ""] {
+            assert!(!confirm(answer).0, "{:?}", answer);
+        }
+    }
+
+    #[test]
+    fn test_confirm_save_shows_entry() {
+        let (_, shown) = confirm("\n");
+        assert!(shown.contains("Title: call-jan.md"));
+        assert!(shown.contains("Content: Call Jan"));
+        assert!(shown.contains("Tags: work"));
+        assert!(shown.ends_with("Save this entry? [Y/n] "));
+    }
+
+    #[test]
+    fn test_positional_content_wins() {
+        let content = read_content(None, Some("note".to_string()), true, true).unwrap();
+        assert_eq!(content, "note");
+    }
+
+    #[test]
+    fn test_input_file_wins_over_positional() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.txt");
+        std::fs::write(&path, "  Call Jan about Q2\n").unwrap();
+
+        let content = read_content(Some(&path), Some("note".to_string()), true, true).unwrap();
+        assert_eq!(content, "Call Jan about Q2");
+    }
+
+    #[test]
+    fn test_input_file_missing_or_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.txt");
+        let err = read_content(Some(&missing), None, false, false).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+
+        let empty = dir.path().join("empty.txt");
+        std::fs::write(&empty, " \n").unwrap();
+        let err = read_content(Some(&empty), None, false, false).unwrap_err();
+        assert!(err.to_string().contains("is empty"));
+    }
+
+    #[test]
+    fn test_content_source_precedence() {
+        // input file > arg > stdin > clipboard
+        assert_eq!(
+            content_source(true, true, true, true, true),
+            Source::InputFile
+        );
+        assert_eq!(
+            content_source(true, false, false, false, false),
+            Source::InputFile
+        );
+        assert_eq!(content_source(false, true, false, false, true), Source::Arg);
+        assert_eq!(content_source(false, true, true, false, true), Source::Arg);
+        assert_eq!(
+            content_source(false, false, false, false, true),
+            Source::Stdin
+        );
+        assert_eq!(
+            content_source(false, false, true, false, true),
+            Source::Clipboard
+        );
+        assert_eq!(
+            content_source(false, false, true, false, false),
+            Source::Stdin
+        );
+        // The editor is asked for explicitly, so it beats the clipboard
+        assert_eq!(
+            content_source(false, false, true, true, true),
+            Source::Editor
+        );
+    }
+
+    #[test]
+    fn test_read_lossy_replaces_invalid_utf8() {
+        let bytes: &[u8] = b"Caf\xe9 with Jan\n\xff";
+        let text = read_lossy(&mut &bytes[..]).unwrap();
+        assert_eq!(text, "Caf\u{FFFD} with Jan\n\u{FFFD}");
+
+        let text = read_lossy(&mut "Café".as_bytes()).unwrap();
+        assert_eq!(text, "Café");
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<!DOCTYPE html>
+<html>
+<head><title>Q2 planning</title><style>p { color: red; }</style></head>
+<body>
+  <header><a href="/">Site name</a></header>
+  <nav><a href="/">Home</a> | <a href="/blog">Blog</a></nav>
+  <article>
+    <header><h1>Q2 planning &amp; budget</h1></header>
+    <p>We met with
+       Jan&nbsp;to talk about the <em>budget</em>.</p>
+    <!-- <p>draft</p> -->
+    <ul><li>Hire two engineers</li><li>Cut travel by 10&#37;</li></ul>
+    <script>if (a < b && c > d) { render("<p>ad</p>"); }</script>
+    <p>Next review: Jan&rsquo;s call<br>on Friday</p>
+  </article>
+  <footer>&copy; 2024</footer>
+</body>
+</html>"#;
+
+        assert_eq!(
+            html_to_text(html),
+            "Q2 planning & budget\n\n\
+             We met with Jan to talk about the budget.\n\n\
+             - Hire two engineers\n\
+             - Cut travel by 10%\n\n\
+             Next review: Jan’s call\n\
+             on Friday"
+        );
+    }
+
+    #[test]
+    fn test_decode_entities_keeps_unknown_ones() {
+        assert_eq!(
+            decode_entities("a &lt;b&gt; &#x41; &bogus; & c"),
+            "a <b> A &bogus; & c"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_url_rejects_non_html() {
+        let mut server = mockito::Server::new_async().await;
+        let page = server
+            .mock("GET", "/article")
+            .with_header("content-type", "text/html; charset=utf-8")
+            .with_body("<p>Call Jan about Q2</p>")
+            .create_async()
+            .await;
+        let pdf = server
+            .mock("GET", "/report.pdf")
+            .with_header("content-type", "application/pdf")
+            .with_body("%PDF-1.7")
+            .create_async()
+            .await;
+
+        let timeout = Duration::from_secs(5);
+        let text = read_url(&format!("{}/article", server.url()), timeout, None)
+            .await
+            .unwrap();
+        assert_eq!(text, "Call Jan about Q2");
+
+        let err = read_url(&format!("{}/report.pdf", server.url()), timeout, None)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("is not an HTML page (application/pdf)"));
+        page.assert_async().await;
+        pdf.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_read_capped_rejects_large_pages() {
+        let mut server = mockito::Server::new_async().await;
+        let _page = server
+            .mock("GET", "/long")
+            .with_body("x".repeat(4096))
+            .create_async()
+            .await;
+        let get = || reqwest::get(format!("{}/long", server.url()));
+
+        let body = read_capped(get().await.unwrap(), 4096).await.unwrap();
+        assert_eq!(body.len(), 4096);
+        let err = read_capped(get().await.unwrap(), 2048).await.unwrap_err();
+        assert_eq!(err.to_string(), "the page is larger than 2 KiB");
+    }
+}
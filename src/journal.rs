@@ -1,36 +1,487 @@
 use anyhow::{anyhow, Context, Result};
-use std::process::Command;
+use chrono::{DateTime, FixedOffset, NaiveDate, SecondsFormat};
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
-/// Check if file-journal is installed and available
-pub fn check_file_journal() -> Result<()> {
-    match Command::new("file-journal").arg("--help").output() {
-        Ok(_) => Ok(()),
-        Err(e) => Err(anyhow!(
-            "file-journal not found in PATH. Please install it first: https://github.com/total70/file-journal\nError: {}",
-            e
-        )),
+use crate::config::{JournalConfig, LineEnding};
+use crate::providers::LlmResponse;
+
+#[derive(Debug, Serialize)]
+struct EntryFrontmatter<'a> {
+    title: &'a str,
+    date: String,
+    tags: &'a [String],
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    metadata: Option<&'a GenerationMetadata>,
+}
+
+/// What produced an entry, recorded when `record_metadata` is on.
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationMetadata {
+    pub generated_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    pub generated_at: String,
+}
+
+impl GenerationMetadata {
+    pub fn new(
+        provider: &str,
+        model: Option<&str>,
+        temperature: Option<f32>,
+        date: &DateTime<FixedOffset>,
+    ) -> Self {
+        Self {
+            generated_by: provider.to_string(),
+            model: model.map(str::to_string),
+            temperature,
+            generated_at: date.to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+
+    /// HTML comment footer for entries saved without frontmatter, hidden when rendered.
+    pub fn footer(&self) -> String {
+        let mut fields = vec![format!("generated_by: {}", self.generated_by)];
+        if let Some(model) = &self.model {
+            fields.push(format!("model: {}", model));
+        }
+        if let Some(temperature) = self.temperature {
+            fields.push(format!("temperature: {}", temperature));
+        }
+        fields.push(format!("generated_at: {}", self.generated_at));
+        format!("<!-- {} -->", fields.join(", "))
+    }
+}
+
+/// The journal command, pointed at `journal_dir` when one is configured.
+pub fn journal_command(journal: &JournalConfig) -> Command {
+    let mut command = Command::new(&journal.command);
+    if let Some(dir) = &journal.journal_dir {
+        command.arg("--dir").arg(dir);
+    }
+    command
+}
+
+/// Root of the journal in use: `journal_dir`, else file-journal's default path.
+pub fn journal_root(journal: &JournalConfig) -> Result<PathBuf> {
+    match &journal.journal_dir {
+        Some(dir) => Ok(dir.clone()),
+        None => crate::todos::read_file_journal_default_path(),
+    }
+}
+
+/// A `major.minor.patch` version as printed by `file-journal --version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// The first `x.y.z` in `output` (e.g. `file-journal 0.3.1`), ignoring a leading `v`
+    /// and any pre-release or build suffix.
+    pub fn parse(output: &str) -> Option<Self> {
+        output.split_whitespace().find_map(|word| {
+            let word = word.strip_prefix('v').unwrap_or(word);
+            let core = word.split(['-', '+']).next()?;
+            let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+            let version = Self::new(parts.next()??, parts.next()??, parts.next()??);
+            parts.next().is_none().then_some(version)
+        })
     }
+
+    /// Whether this version meets `requirement`: comma-separated comparisons such as
+    /// `>=0.1.0, <1.0.0`.
+    pub fn matches(&self, requirement: &str) -> Result<bool> {
+        for comparison in requirement.split(',').map(str::trim) {
+            let (op, version) = [">=", "<=", ">", "<", "="]
+                .iter()
+                .find_map(|op| Some((*op, comparison.strip_prefix(op)?)))
+                .ok_or_else(|| {
+                    anyhow!("Expected a comparison like '>=0.1.0', got '{}'", comparison)
+                })?;
+            let version = Self::parse(version.trim())
+                .ok_or_else(|| anyhow!("Expected a x.y.z version in '{}'", comparison))?;
+            let ok = match op {
+                ">=" => *self >= version,
+                "<=" => *self <= version,
+                ">" => *self > version,
+                "<" => *self < version,
+                _ => *self == version,
+            };
+            if !ok {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// What `file_journal_info` found out about the installed journal command.
+#[derive(Debug, Default)]
+pub struct JournalInfo {
+    /// Reported by `file-journal --version`; `None` for custom commands or unparseable output
+    pub version: Option<Version>,
+}
+
+impl JournalInfo {
+    /// A warning when file-journal reports a version outside `supported`
+    /// (`journal.file_journal_version`).
+    pub fn warning(&self, supported: Option<&str>) -> Option<String> {
+        let supported = supported?;
+        let version = self.version?;
+        match version.matches(supported) {
+            Ok(true) => None,
+            Ok(false) => Some(format!(
+                "file-journal {} is outside the supported versions ({}); saving may fail",
+                version, supported
+            )),
+            Err(e) => Some(format!("journal.file_journal_version: {}", e)),
+        }
+    }
+}
+
+/// Check if the journal command (file-journal by default) is installed and available
+pub fn check_file_journal(journal: &JournalConfig) -> Result<()> {
+    if let Some(dir) = &journal.journal_dir {
+        if !dir.is_dir() {
+            return Err(anyhow!(
+                "Journal directory {} does not exist",
+                dir.display()
+            ));
+        }
+    }
+    match journal_command(journal).arg("--help").output() {
+        Ok(_) => {}
+        Err(e) if journal.command == "file-journal" => {
+            return Err(anyhow!(
+                "file-journal not found in PATH. Please install it first: https://github.com/total70/file-journal\nError: {}",
+                e
+            ))
+        }
+        Err(e) => return Err(anyhow!("{} not found in PATH\nError: {}", journal.command, e)),
+    }
+    Ok(())
+}
+
+/// The installed file-journal's version, from `file-journal --version`. Only `doctor`
+/// asks, so saving an entry doesn't pay for the extra process.
+pub fn file_journal_info(journal: &JournalConfig) -> JournalInfo {
+    // A custom command's version says nothing about its argument layout
+    if journal.command != "file-journal" {
+        return JournalInfo::default();
+    }
+    let version = Command::new(&journal.command)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| Version::parse(&String::from_utf8_lossy(&output.stdout)));
+    JournalInfo { version }
+}
+
+/// Arguments for creating an entry, with `{title}` and `{content}` substituted
+/// into the configured `new_args` template.
+///
+/// With `content_via_stdin`, arguments carrying `{content}` are left out.
+pub fn build_new_args(journal: &JournalConfig, title: &str, content: &str) -> Vec<String> {
+    new_arg_templates(journal)
+        .map(|arg| arg.replace("{title}", title).replace("{content}", content))
+        .collect()
+}
+
+fn new_arg_templates(journal: &JournalConfig) -> impl Iterator<Item = &String> {
+    journal
+        .new_args
+        .iter()
+        .filter(|arg| !(journal.content_via_stdin && arg.contains("{content}")))
 }
 
-/// Create a journal entry using file-journal
-pub fn create_entry(title: &str, content: &str) -> Result<String> {
-    // Ensure title ends with .md
-    let title = if title.ends_with(".md") {
+/// `title` ending in `.{extension}`; one already there is not added again.
+fn with_extension(title: &str, extension: &str) -> String {
+    if title.ends_with(&format!(".{}", extension)) {
         title.to_string()
     } else {
-        format!("{}.md", title)
+        format!("{}.{}", title, extension)
+    }
+}
+
+/// `title` without its `.{extension}`.
+pub fn title_stem<'a>(title: &'a str, extension: &str) -> &'a str {
+    title
+        .strip_suffix(extension)
+        .and_then(|stem| stem.strip_suffix('.'))
+        .unwrap_or(title)
+}
+
+/// `title` if it is free, otherwise the first free `title-2.md`, `title-3.md`, ...
+pub fn unique_title(title: &str, extension: &str, taken: impl Fn(&str) -> bool) -> String {
+    let title = with_extension(title, extension);
+    if !taken(&title) {
+        return title;
+    }
+    let stem = title_stem(&title, extension);
+    (2..)
+        .map(|n| format!("{}-{}.{}", stem, n, extension))
+        .find(|candidate| !taken(candidate))
+        .expect("unbounded suffixes")
+}
+
+/// Whether file-journal already saved an entry titled `title` on `date`, i.e. a
+/// `YYYY/MM/dd-HHMMSS-title.md` file exists under the journal root.
+pub fn title_taken(journal_root: &Path, date: NaiveDate, title: &str, extension: &str) -> bool {
+    !entries_titled(journal_root, date, title, extension).is_empty()
+}
+
+/// Entries saved on `date` under `title`.
+pub fn entries_titled(
+    journal_root: &Path,
+    date: NaiveDate,
+    title: &str,
+    extension: &str,
+) -> Vec<PathBuf> {
+    let dir = journal_root
+        .join(date.format("%Y").to_string())
+        .join(date.format("%m").to_string());
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return vec![];
     };
+    let day = date.format("%d-").to_string();
+    let title = with_extension(title, extension);
+    let mut matching: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix(&day)
+                .and_then(|rest| rest.split_once('-'))
+                .is_some_and(|(time, rest)| {
+                    time.chars().all(|c| c.is_ascii_digit()) && rest == title
+                })
+        })
+        .map(|entry| entry.path())
+        .collect();
+    matching.sort();
+    matching
+}
 
-    let output = Command::new("file-journal")
-        .arg("new")
-        .arg(&title)
-        .arg(content)
-        .output()
-        .context("Failed to execute file-journal. Is it installed?")?;
+/// Where `backup_entry` copies `file`: the same path with `.bak` appended.
+pub fn backup_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// Copy `file` to `<file>.bak` before it is modified, replacing an older backup.
+pub fn backup_entry(file: &Path) -> Result<PathBuf> {
+    let backup = backup_path(file);
+    fs::copy(file, &backup).with_context(|| {
+        format!(
+            "Failed to back up {} to {}",
+            file.display(),
+            backup.display()
+        )
+    })?;
+    Ok(backup)
+}
+
+/// Replace `file` with `contents` all at once: they are written to a temporary file
+/// next to it that is then renamed over it, so an interrupted run leaves either the
+/// old or the new file, never half of one.
+pub fn write_atomic(file: &Path, contents: &str) -> Result<()> {
+    write_atomic_with(file, |out| out.write_all(contents.as_bytes()))
+}
+
+/// Run `update` while holding an exclusive lock on `<file>.lock`, so read-modify-write
+/// cycles on `file` from parallel jobs or separate runs don't lose each other's changes.
+/// The lock lives in its own file because `write_atomic` replaces `file` itself.
+pub fn with_file_lock<T>(file: &Path, update: impl FnOnce() -> Result<T>) -> Result<T> {
+    if let Some(dir) = file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let mut name = file.as_os_str().to_owned();
+    name.push(".lock");
+    let lock_path = PathBuf::from(name);
+    let lock = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    // Released when `lock` is dropped
+    lock.lock()
+        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+    update()
+}
+
+fn write_atomic_with(
+    file: &Path,
+    write: impl FnOnce(&mut fs::File) -> std::io::Result<()>,
+) -> Result<()> {
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut temp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("Failed to write {}", file.display()))?;
+    // Keep the entry's permissions rather than the temporary file's private ones
+    if let Ok(metadata) = fs::metadata(file) {
+        temp.as_file().set_permissions(metadata.permissions())?;
+    }
+    write(temp.as_file_mut())
+        .and_then(|_| temp.as_file().sync_all())
+        .with_context(|| format!("Failed to write {}", file.display()))?;
+    temp.persist(file)
+        .with_context(|| format!("Failed to write {}", file.display()))?;
+    Ok(())
+}
+
+/// Describe the backup `backup_entry` would make (for dry runs).
+pub fn backup_entry_dry_run(file: &Path) -> String {
+    format!(
+        "[DRY RUN] Would back up:\n  {} -> {}",
+        file.display(),
+        backup_path(file).display()
+    )
+}
+
+/// Normalize whitespace in content before it is saved.
+///
+/// Trailing whitespace is stripped from every line, except a Markdown hard break (two
+/// or more spaces before the next line of the same paragraph), which is kept as two
+/// spaces. Trailing blank lines are dropped, lines are joined with the configured line
+/// ending, and a final newline is added when `trailing_newline` is set.
+pub fn normalize_content(content: &str, trailing_newline: bool, line_ending: LineEnding) -> String {
+    let eol = line_ending.as_str();
+    let raw: Vec<&str> = content.lines().collect();
+    let lines: Vec<String> = raw
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let trimmed = line.trim_end();
+            let next_continues = raw.get(i + 1).is_some_and(|next| !next.trim().is_empty());
+            if line.ends_with("  ") && !trimmed.is_empty() && next_continues {
+                format!("{}  ", trimmed)
+            } else {
+                trimmed.to_string()
+            }
+        })
+        .collect();
+    let mut out = lines.join(eol).trim_end().to_string();
+    if trailing_newline && !out.is_empty() {
+        out.push_str(eol);
+    }
+    out
+}
+
+/// Prepend a YAML frontmatter block (title, date, tags and any generation metadata)
+/// to the entry content.
+pub fn render_with_frontmatter(
+    response: &LlmResponse,
+    extension: &str,
+    date: &DateTime<FixedOffset>,
+    metadata: Option<&GenerationMetadata>,
+) -> String {
+    let fm = EntryFrontmatter {
+        title: title_stem(&response.title, extension),
+        date: date.to_rfc3339_opts(SecondsFormat::Secs, true),
+        tags: &response.tags,
+        metadata,
+    };
+    // Serializing plain strings cannot fail
+    let yaml = serde_yaml::to_string(&fm).unwrap_or_default();
+    format!("---\n{}---\n\n{}", yaml, response.content.trim_start())
+}
+
+/// End `content` with the configured `footer`, its `{date}` and `{time}` filled in from
+/// `now`. No footer, or a blank one, leaves the content as it is.
+pub fn with_footer(content: &str, footer: Option<&str>, now: &DateTime<FixedOffset>) -> String {
+    match footer.map(str::trim).filter(|footer| !footer.is_empty()) {
+        Some(footer) => {
+            let footer = footer
+                .replace("{date}", &now.format("%Y-%m-%d").to_string())
+                .replace("{time}", &now.format("%H:%M").to_string());
+            format!("{}\n\n{}", content.trim_end(), footer)
+        }
+        None => content.to_string(),
+    }
+}
+
+/// Prefix a title with `YYYY-MM-DD-` so files sort chronologically.
+///
+/// Titles that already start with a date are returned unchanged.
+pub fn date_prefixed_title(title: &str, date: NaiveDate) -> String {
+    let already_dated = title
+        .get(..10)
+        .is_some_and(|p| NaiveDate::parse_from_str(p, "%Y-%m-%d").is_ok());
+    if already_dated {
+        title.to_string()
+    } else {
+        format!("{}-{}", date.format("%Y-%m-%d"), title)
+    }
+}
+
+/// The command `create_entry` runs, shared with its dry run so the two can't drift.
+fn entry_command(journal: &JournalConfig, title: &str, content: &str) -> Command {
+    let mut command = journal_command(journal);
+    command.args(build_new_args(journal, title, content));
+    command
+}
+
+/// Create a journal entry using the configured journal command
+pub fn create_entry(journal: &JournalConfig, title: &str, content: &str) -> Result<String> {
+    let title = with_extension(title, &journal.extension);
+
+    let mut command = entry_command(journal, &title, content);
+
+    let output = if journal.content_via_stdin {
+        // Long entries would hit the argv size limit, so stream them instead
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to execute {}. Is it installed?", journal.command))?;
+        let mut stdin = child.stdin.take().context("Failed to open stdin")?;
+        // Write from a separate thread so a chatty child can't deadlock on full pipes
+        std::thread::scope(|scope| {
+            let writer = scope.spawn(move || stdin.write_all(content.as_bytes()));
+            let output = child.wait_with_output();
+            match writer.join() {
+                // A backend that exits without reading all of stdin is not our error
+                Ok(Err(e)) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e),
+                _ => output,
+            }
+        })
+        .with_context(|| format!("Failed to pass content to {}", journal.command))?
+    } else {
+        command
+            .output()
+            .with_context(|| format!("Failed to execute {}. Is it installed?", journal.command))?
+    };
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("file-journal failed: {}", stderr));
+        return Err(anyhow!("{} failed: {}", journal.command, stderr));
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -38,38 +489,1046 @@ pub fn create_entry(title: &str, content: &str) -> Result<String> {
 }
 
 /// Create a journal entry with dry-run (for testing)
-pub fn create_entry_dry_run(title: &str, content: &str) -> Result<String> {
-    let title = if title.ends_with(".md") {
-        title.to_string()
+pub fn create_entry_dry_run(journal: &JournalConfig, title: &str, content: &str) -> Result<String> {
+    let title = with_extension(title, &journal.extension);
+    let command = entry_command(journal, &title, content);
+    let mut line: Vec<String> = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| shell_quote(&arg.to_string_lossy()))
+        .collect();
+    if journal.content_via_stdin {
+        line.push("(content on stdin)".to_string());
+    }
+    let directory = journal.journal_dir.as_ref().map_or_else(
+        || format!("{} default", journal.command),
+        |dir| dir.display().to_string(),
+    );
+
+    Ok(format!(
+        "[DRY RUN] Would create:\n  Title: {}\n  Content: {}\n  Directory: {}\n  Command: {}",
+        title,
+        content,
+        directory,
+        line.join(" ")
+    ))
+}
+
+/// `arg` as a POSIX shell would need it: unchanged when it has no special characters,
+/// single-quoted otherwise.
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
     } else {
-        format!("{}.md", title)
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Append content to an existing entry under a timestamped heading.
+///
+/// When the entry has YAML frontmatter, `tags` are merged into its `tags` list.
+pub fn append_entry(
+    file: &Path,
+    content: &str,
+    tags: &[String],
+    now: &DateTime<FixedOffset>,
+) -> Result<String> {
+    let existing =
+        fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let heading = now.format("%Y-%m-%d %H:%M").to_string();
+    let updated = append_to(&existing, content, tags, &heading)?;
+    write_atomic(file, &updated)?;
+    Ok(format!("Appended to journal entry: {}", file.display()))
+}
+
+/// Today's file in daily mode: `YYYY/MM/YYYY-MM-DD.<ext>` under the journal root.
+pub fn daily_path(journal_root: &Path, date: NaiveDate, extension: &str) -> PathBuf {
+    journal_root
+        .join(date.format("%Y").to_string())
+        .join(date.format("%m").to_string())
+        .join(format!("{}.{}", date.format("%Y-%m-%d"), extension))
+}
+
+/// Append content to the day's file under a `## HH:MM` heading, creating the file
+/// (with the tags as frontmatter) when this is the day's first note.
+pub fn append_daily(
+    file: &Path,
+    content: &str,
+    tags: &[String],
+    now: &DateTime<FixedOffset>,
+) -> Result<String> {
+    let heading = now.format("%H:%M").to_string();
+    let (updated, verb) = if file.exists() {
+        let existing = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        (
+            append_to(&existing, content, tags, &heading)?,
+            "Appended to",
+        )
+    } else {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        (daily_start(content, tags, &heading)?, "Created")
     };
+    write_atomic(file, &updated)?;
+    Ok(format!("{} journal entry: {}", verb, file.display()))
+}
 
+/// Append to the day's file with dry-run (for testing)
+pub fn append_daily_dry_run(file: &Path, content: &str) -> String {
+    let action = if file.exists() { "append to" } else { "create" };
+    format!(
+        "[DRY RUN] Would {}:\n  File: {}\n  Content: {}",
+        action,
+        file.display(),
+        content
+    )
+}
+
+/// The first note of the day: frontmatter only when there are tags to keep.
+fn daily_start(content: &str, tags: &[String], heading: &str) -> Result<String> {
+    let section = format!("## {}\n\n{}\n", heading, content.trim());
+    if tags.is_empty() {
+        return Ok(section);
+    }
+    Ok(format!("---\n{}---\n\n{}", merge_tags("", tags)?, section))
+}
+
+/// Merge `tags` into the frontmatter of an existing entry, adding frontmatter when it
+/// has none. The body is left untouched.
+pub fn add_tags(file: &Path, tags: &[String]) -> Result<String> {
+    let existing =
+        fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let updated = match frontmatter_parts(&existing) {
+        Some((yaml, body)) => format!("---\n{}---\n{}", merge_tags(yaml, tags)?, body),
+        None => format!("---\n{}---\n\n{}", merge_tags("", tags)?, existing),
+    };
+    write_atomic(file, &updated)?;
+    Ok(format!("Tagged journal entry: {}", file.display()))
+}
+
+/// Append to an entry with dry-run (for testing)
+pub fn append_entry_dry_run(file: &Path, content: &str) -> Result<String> {
+    if !file.is_file() {
+        return Err(anyhow!("Journal entry {} does not exist", file.display()));
+    }
     Ok(format!(
-        "[DRY RUN] Would create:\n  Title: {}\n  Content: {}\n  Command: file-journal new '{}' '{}'",
-        title, content, title, content
+        "[DRY RUN] Would append to:\n  File: {}\n  Content: {}",
+        file.display(),
+        content
     ))
 }
 
+fn append_to(existing: &str, content: &str, tags: &[String], heading: &str) -> Result<String> {
+    let existing = match frontmatter_parts(existing) {
+        Some((yaml, body)) if !tags.is_empty() => {
+            format!("---\n{}---\n{}", merge_tags(yaml, tags)?, body)
+        }
+        _ => existing.to_string(),
+    };
+    Ok(format!(
+        "{}\n\n## {}\n\n{}\n",
+        existing.trim_end(),
+        heading,
+        content.trim()
+    ))
+}
+
+/// Split `---\n<yaml>---\n<body>` into its YAML and body.
+fn frontmatter_parts(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix("---\n")?;
+    if let Some(body) = rest.strip_prefix("---\n") {
+        return Some(("", body));
+    }
+    let end = rest.find("\n---\n")?;
+    Some((&rest[..end + 1], &rest[end + 5..]))
+}
+
+/// Add `tags` missing from the frontmatter's `tags` list, keeping the other fields.
+fn merge_tags(yaml: &str, tags: &[String]) -> Result<String> {
+    let mut fm: serde_yaml::Mapping = if yaml.trim().is_empty() {
+        serde_yaml::Mapping::new()
+    } else {
+        serde_yaml::from_str(yaml).context("Failed to parse entry frontmatter")?
+    };
+    let key = serde_yaml::Value::from("tags");
+    let mut merged = fm
+        .get(&key)
+        .and_then(|v| v.as_sequence())
+        .cloned()
+        .unwrap_or_default();
+    for tag in tags {
+        let tag = serde_yaml::Value::from(tag.as_str());
+        if !merged.contains(&tag) {
+            merged.push(tag);
+        }
+    }
+    fm.insert(key, serde_yaml::Value::Sequence(merged));
+    serde_yaml::to_string(&fm).context("Failed to write entry frontmatter")
+}
+
+/// List all journal entry files (those ending in `.<extension>`) under the journal root,
+/// sorted by path.
+///
+/// Entries live at `YYYY/MM/dd-HHMMSS-title.md`, so path order is chronological.
+/// The `todos/` directory and hidden files are skipped.
+pub fn list_entries(journal_root: &Path, extension: &str) -> Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, root: &Path, extension: &str, out: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in fs::read_dir(dir)
+            .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if name.starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                if dir == root && name == "todos" {
+                    continue;
+                }
+                walk(&path, root, extension, out)?;
+            } else if path.extension().is_some_and(|e| e == extension) {
+                out.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    if !journal_root.exists() {
+        return Err(anyhow!(
+            "Journal path {} does not exist",
+            journal_root.display()
+        ));
+    }
+
+    let mut entries = Vec::new();
+    walk(journal_root, journal_root, extension, &mut entries)?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// One entry as shown by `journal-ai list`.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntrySummary {
+    pub path: PathBuf,
+    pub date: Option<NaiveDate>,
+    pub title: String,
+    pub tags: Vec<String>,
+}
+
+impl EntrySummary {
+    /// Summary of the entry at `path`; tags come from its frontmatter when the file
+    /// can be read.
+    pub fn from_path(path: PathBuf) -> Self {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // file-journal names entries `dd-HHMMSS-title.md`
+        let title = match name.split_once('-') {
+            Some((day, rest)) if day.len() == 2 && day.chars().all(|c| c.is_ascii_digit()) => rest
+                .split_once('-')
+                .filter(|(time, _)| time.chars().all(|c| c.is_ascii_digit()))
+                .map_or(rest, |(_, title)| title)
+                .to_string(),
+            _ => name,
+        };
+        let tags = fs::read_to_string(&path)
+            .map(|text| frontmatter_tags(&text))
+            .unwrap_or_default();
+        Self {
+            date: crate::summarize::entry_date(&path),
+            path,
+            title,
+            tags,
+        }
+    }
+}
+
+/// The `tags` list from an entry's frontmatter; empty when there is none.
+fn frontmatter_tags(text: &str) -> Vec<String> {
+    frontmatter_parts(text)
+        .and_then(|(yaml, _)| serde_yaml::from_str::<serde_yaml::Value>(yaml).ok())
+        .and_then(|fm| {
+            fm.get("tags")?.as_sequence().map(|tags| {
+                tags.iter()
+                    .filter_map(|t| t.as_str().map(str::to_string))
+                    .collect()
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Entry paths (ending in `.<extension>`) from `<command> list` output, one per line.
+/// Bullets and other lines are ignored; relative paths are resolved against the
+/// journal root.
+pub fn parse_list_output(output: &str, journal_root: &Path, extension: &str) -> Vec<PathBuf> {
+    let suffix = format!(".{}", extension);
+    output
+        .lines()
+        .map(|line| line.trim().trim_start_matches(['-', '*']).trim_start())
+        .filter(|line| line.ends_with(&suffix))
+        .map(|line| journal_root.join(line))
+        .collect()
+}
+
+/// Keep entries tagged `tag`, ignoring case and a leading `#`.
+pub fn filter_by_tag(entries: Vec<EntrySummary>, tag: &str) -> Vec<EntrySummary> {
+    let tag = tag.trim_start_matches('#');
+    entries
+        .into_iter()
+        .filter(|entry| {
+            entry
+                .tags
+                .iter()
+                .any(|t| t.trim_start_matches('#').eq_ignore_ascii_case(tag))
+        })
+        .collect()
+}
+
+/// Every entry, oldest first, as listed by `<command> list`. Falls back to scanning
+/// the journal root when the command has no `list` or fails.
+pub fn list_summaries(journal: &JournalConfig, journal_root: &Path) -> Result<Vec<EntrySummary>> {
+    let listed = journal_command(journal)
+        .arg("list")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            let output = String::from_utf8_lossy(&output.stdout);
+            parse_list_output(&output, journal_root, &journal.extension)
+        });
+    let mut paths = match listed {
+        Some(paths) => paths,
+        None => {
+            tracing::info!(
+                "{} list unavailable, scanning {}",
+                journal.command,
+                journal_root.display()
+            );
+            list_entries(journal_root, &journal.extension)?
+        }
+    };
+    paths.sort();
+    Ok(paths.into_iter().map(EntrySummary::from_path).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_version_output() {
+        assert_eq!(
+            Version::parse("file-journal 0.3.1\n"),
+            Some(Version::new(0, 3, 1))
+        );
+        assert_eq!(Version::parse("v1.2.0"), Some(Version::new(1, 2, 0)));
+        assert_eq!(
+            Version::parse("file-journal 0.4.0-beta.2 (abc123)"),
+            Some(Version::new(0, 4, 0))
+        );
+        assert_eq!(Version::parse("file-journal 0.3"), None);
+        assert_eq!(Version::parse("file-journal 1.2.3.4"), None);
+        assert_eq!(Version::parse("Usage: file-journal <COMMAND>"), None);
+    }
+
+    #[test]
+    fn test_supported_version_range() {
+        let range = ">=0.1.0, <1.0.0";
+        assert!(Version::new(0, 1, 0).matches(range).unwrap());
+        assert!(Version::new(0, 9, 12).matches(range).unwrap());
+        assert!(!Version::new(0, 0, 9).matches(range).unwrap());
+        assert!(!Version::new(1, 0, 0).matches(range).unwrap());
+        assert!(Version::new(0, 10, 0) > Version::new(0, 9, 99));
+        assert!(Version::new(0, 3, 1).matches("=0.3.1").unwrap());
+        assert!(Version::new(0, 3, 1).matches("~0.3").is_err());
+
+        let info = JournalInfo {
+            version: Some(Version::new(2, 0, 0)),
+        };
+        assert!(info
+            .warning(Some(range))
+            .unwrap()
+            .contains("file-journal 2.0.0"));
+        // Without a configured range any version is fine
+        assert!(info.warning(None).is_none());
+        assert!(JournalInfo::default().warning(Some(range)).is_none());
+    }
+
     #[test]
     fn test_create_entry_dry_run() {
-        let result = create_entry_dry_run("test-title", "Test content");
+        let result = create_entry_dry_run(&JournalConfig::default(), "test-title", "Test content");
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.contains("test-title.md"));
         assert!(output.contains("Test content"));
+        assert!(output.contains("Command: file-journal new test-title.md (content on stdin)"));
+    }
+
+    #[test]
+    fn test_create_entry_dry_run_custom_extension() {
+        for extension in ["txt", "org"] {
+            let journal = JournalConfig {
+                extension: extension.to_string(),
+                ..JournalConfig::default()
+            };
+            let expected = format!(
+                "Command: file-journal new notes.{} (content on stdin)",
+                extension
+            );
+            // No second extension when the title already has one
+            for title in ["notes".to_string(), format!("notes.{}", extension)] {
+                let output = create_entry_dry_run(&journal, &title, "Test content").unwrap();
+                assert!(output.contains(&expected), "{}", output);
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_extension_titles() {
+        assert_eq!(unique_title("notes", "txt", |_| false), "notes.txt");
+        assert_eq!(
+            unique_title("notes.txt", "txt", |t| t == "notes.txt"),
+            "notes-2.txt"
+        );
+        assert_eq!(title_stem("notes.org", "org"), "notes");
+        assert_eq!(title_stem("notes.md", "org"), "notes.md");
+        assert_eq!(title_stem("backorg", "org"), "backorg");
+    }
+
+    #[test]
+    fn test_append_entry_dry_run() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let output = append_entry_dry_run(file.path(), "Test content").unwrap();
+        assert!(output.contains(&format!("File: {}", file.path().display())));
+        assert!(output.contains("Test content"));
+
+        assert!(append_entry_dry_run(Path::new("/no/such/entry.md"), "Test content").is_err());
+    }
+
+    #[test]
+    fn test_interrupted_write_leaves_original_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("25-220255-team-sync.md");
+        fs::write(&file, "Morning notes.\n").unwrap();
+
+        let failed = write_atomic_with(&file, |out| {
+            out.write_all(b"Morning notes.\n\n## 18:05\n\nHalf")?;
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "interrupted",
+            ))
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "Morning notes.\n");
+        // The temporary file is cleaned up too
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        write_atomic(&file, "Morning notes.\n\nMore.\n").unwrap();
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "Morning notes.\n\nMore.\n"
+        );
+    }
+
+    #[test]
+    fn test_append_entry_adds_timestamped_section() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), "Morning notes.\n").unwrap();
+        let now = DateTime::parse_from_rfc3339("2026-02-25T18:05:00+01:00").unwrap();
+
+        let result = append_entry(file.path(), "Evening update.", &[], &now).unwrap();
+        assert!(result.ends_with(&file.path().display().to_string()));
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "Morning notes.\n\n## 2026-02-25 18:05\n\nEvening update.\n"
+        );
+    }
+
+    #[test]
+    fn test_daily_path_and_heading() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 7).unwrap();
+        assert_eq!(
+            daily_path(Path::new("/journal"), date, "md"),
+            Path::new("/journal/2026/03/2026-03-07.md")
+        );
+        assert_eq!(
+            daily_start("  Coffee with Sam.\n", &[], "08:30").unwrap(),
+            "## 08:30\n\nCoffee with Sam.\n"
+        );
+    }
+
+    #[test]
+    fn test_append_daily_creates_then_appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 3, 7).unwrap();
+        let file = daily_path(dir.path(), date, "md");
+        let morning = DateTime::parse_from_rfc3339("2026-03-07T08:30:00+01:00").unwrap();
+        let evening = DateTime::parse_from_rfc3339("2026-03-07T21:15:00+01:00").unwrap();
+
+        assert!(append_daily_dry_run(&file, "First.").starts_with("[DRY RUN] Would create:"));
+        let result = append_daily(&file, "First.", &["work".to_string()], &morning).unwrap();
+        assert!(result.starts_with("Created journal entry: "));
+        assert!(append_daily_dry_run(&file, "Second.").starts_with("[DRY RUN] Would append to:"));
+        let result = append_daily(&file, "Second.", &["home".to_string()], &evening).unwrap();
+        assert!(result.starts_with("Appended to journal entry: "));
+
+        let written = fs::read_to_string(&file).unwrap();
+        let (fm, body) = split_frontmatter(&written);
+        assert_eq!(
+            fm["tags"],
+            serde_yaml::from_str::<serde_yaml::Value>("[work, home]").unwrap()
+        );
+        assert_eq!(body, "\n## 08:30\n\nFirst.\n\n## 21:15\n\nSecond.\n");
+    }
+
+    #[test]
+    fn test_append_merges_tags_into_frontmatter() {
+        let existing = "---\ntitle: team-sync\ntags:\n- work\n---\n\nMorning notes.\n";
+        let tags = vec!["work".to_string(), "q1".to_string()];
+
+        let updated = append_to(existing, "More.", &tags, "2026-02-25 18:05").unwrap();
+        let (fm, body) = split_frontmatter(&updated);
+        assert_eq!(fm["title"], "team-sync");
+        assert_eq!(
+            fm["tags"],
+            serde_yaml::from_str::<serde_yaml::Value>("[work, q1]").unwrap()
+        );
+        assert_eq!(body, "\nMorning notes.\n\n## 2026-02-25 18:05\n\nMore.\n");
+
+        // Without frontmatter the tags are dropped rather than invented
+        let plain = append_to("Notes.", "More.", &tags, "2026-02-25 18:05").unwrap();
+        assert!(plain.starts_with("Notes.\n\n## "));
+    }
+
+    #[test]
+    fn test_build_new_args_default() {
+        let args = build_new_args(&JournalConfig::default(), "a.md", "Hello 'world'");
+        assert_eq!(args, vec!["new", "a.md"]);
+
+        let positional = JournalConfig {
+            content_via_stdin: false,
+            ..JournalConfig::default()
+        };
+        let args = build_new_args(&positional, "a.md", "Hello 'world'");
+        assert_eq!(args, vec!["new", "a.md", "Hello 'world'"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_entry_streams_large_content_via_stdin() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let stub = dir.path().join("stub-journal");
+        fs::write(
+            &stub,
+            "#!/bin/sh\nbytes=$(wc -c | tr -d ' ')\necho \"Created journal entry: /tmp/$2 ($bytes bytes)\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let journal = JournalConfig {
+            command: stub.to_string_lossy().to_string(),
+            ..JournalConfig::default()
+        };
+        let content = "lorem ipsum ".repeat(300_000);
+        let result = create_entry(&journal, "big", &content).unwrap();
+        assert_eq!(
+            result,
+            format!(
+                "Created journal entry: /tmp/big.md ({} bytes)",
+                content.len()
+            )
+        );
+    }
+
+    /// The `Command:` line of a dry run.
+    fn dry_run_command(journal: &JournalConfig, title: &str, content: &str) -> String {
+        let dry_run = create_entry_dry_run(journal, title, content).unwrap();
+        dry_run
+            .lines()
+            .find_map(|l| l.trim().strip_prefix("Command: "))
+            .unwrap()
+            .to_string()
+    }
+
+    /// The argv `create_entry` would spawn, quoted for a shell.
+    fn real_argv(journal: &JournalConfig, title: &str, content: &str) -> Vec<String> {
+        let title = with_extension(title, &journal.extension);
+        let command = entry_command(journal, &title, content);
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|a| shell_quote(&a.to_string_lossy()))
+            .collect()
+    }
+
+    #[test]
+    fn test_dry_run_matches_real_argv() {
+        // stdin mode: the content is not in argv
+        let journal = JournalConfig::default();
+        let mut expected = real_argv(&journal, "team sync", "It's done");
+        assert_eq!(expected, ["file-journal", "new", "'team sync.md'"]);
+        expected.push("(content on stdin)".to_string());
+        assert_eq!(
+            dry_run_command(&journal, "team sync", "It's done"),
+            expected.join(" ")
+        );
+
+        // Positional mode: the content is an argument, quoted the way a shell needs it
+        let journal = JournalConfig {
+            content_via_stdin: false,
+            journal_dir: Some(PathBuf::from("/home/me/work journal")),
+            ..JournalConfig::default()
+        };
+        let expected = real_argv(&journal, "team-sync", "It's done");
+        assert_eq!(
+            expected,
+            [
+                "file-journal",
+                "--dir",
+                "'/home/me/work journal'",
+                "new",
+                "team-sync.md",
+                "'It'\\''s done'",
+            ]
+        );
+        assert_eq!(
+            dry_run_command(&journal, "team-sync", "It's done"),
+            expected.join(" ")
+        );
+    }
+
+    #[test]
+    fn test_build_new_args_custom_template() {
+        let journal = JournalConfig {
+            command: "my-notes".to_string(),
+            new_args: vec![
+                "add".to_string(),
+                "--name={title}".to_string(),
+                "--body".to_string(),
+                "{content}".to_string(),
+            ],
+            content_via_stdin: false,
+            ..JournalConfig::default()
+        };
+        let args = build_new_args(&journal, "call-jan.md", "Call Jan");
+        assert_eq!(
+            args,
+            vec!["add", "--name=call-jan.md", "--body", "Call Jan"]
+        );
+
+        let dry_run = create_entry_dry_run(&journal, "call-jan", "Call Jan").unwrap();
+        assert!(dry_run.contains("Command: my-notes add --name=call-jan.md --body 'Call Jan'"));
+    }
+
+    #[test]
+    fn test_journal_dir_reaches_the_command() {
+        let journal = JournalConfig {
+            journal_dir: Some(PathBuf::from("/home/me/work-journal")),
+            ..JournalConfig::default()
+        };
+        let command = journal_command(&journal);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["--dir", "/home/me/work-journal"]);
+        assert_eq!(
+            journal_root(&journal).unwrap(),
+            PathBuf::from("/home/me/work-journal")
+        );
+
+        let dry_run = create_entry_dry_run(&journal, "call-jan", "Call Jan").unwrap();
+        assert!(dry_run.contains("Directory: /home/me/work-journal"));
+        assert!(dry_run.contains(
+            "Command: file-journal --dir /home/me/work-journal new call-jan.md (content on stdin)"
+        ));
+
+        // Without one the command is left alone
+        assert_eq!(
+            journal_command(&JournalConfig::default())
+                .get_args()
+                .count(),
+            0
+        );
+        let dry_run = create_entry_dry_run(&JournalConfig::default(), "x", "y").unwrap();
+        assert!(dry_run.contains("Directory: file-journal default"));
+    }
+
+    #[test]
+    fn test_check_file_journal_rejects_missing_dir() {
+        let journal = JournalConfig {
+            journal_dir: Some(PathBuf::from("/does/not/exist")),
+            ..JournalConfig::default()
+        };
+        let err = check_file_journal(&journal).unwrap_err();
+        assert!(err.to_string().contains("/does/not/exist"));
+    }
+
+    #[test]
+    fn test_backup_keeps_original_before_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("25-220255-team-sync.md");
+        fs::write(&file, "Hand-written notes\n").unwrap();
+
+        let backup = backup_entry(&file).unwrap();
+        assert_eq!(backup, dir.path().join("25-220255-team-sync.md.bak"));
+
+        let now = DateTime::parse_from_rfc3339("2026-02-25T23:30:00+01:00").unwrap();
+        append_entry(&file, "Follow-up", &[], &now).unwrap();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "Hand-written notes\n");
+        assert!(fs::read_to_string(&file).unwrap().contains("Follow-up"));
+
+        // Backups are not entries
+        assert_eq!(list_entries(dir.path(), "md").unwrap(), vec![file.clone()]);
+        assert!(backup_entry_dry_run(&file).contains("25-220255-team-sync.md.bak"));
+        assert!(backup_entry(&dir.path().join("missing.md")).is_err());
+    }
+
+    #[test]
+    fn test_add_tags_to_frontmatter() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("plain.md");
+        fs::write(&plain, "Just notes\n").unwrap();
+        add_tags(&plain, &["work".to_string()]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&plain).unwrap(),
+            "---\ntags:\n- work\n---\n\nJust notes\n"
+        );
+
+        let with_fm = dir.path().join("fm.md");
+        fs::write(&with_fm, "---\ntitle: sync\ntags:\n- work\n---\n\nBody\n").unwrap();
+        add_tags(&with_fm, &["work".to_string(), "q2".to_string()]).unwrap();
+        let text = fs::read_to_string(&with_fm).unwrap();
+        assert_eq!(frontmatter_tags(&text), vec!["work", "q2"]);
+        assert!(text.ends_with("\n\nBody\n"));
+    }
+
+    #[test]
+    fn test_entries_titled() {
+        let dir = tempfile::tempdir().unwrap();
+        let month = dir.path().join("2026/02");
+        fs::create_dir_all(&month).unwrap();
+        fs::write(month.join("25-080000-sync.md"), "a").unwrap();
+        fs::write(month.join("25-090000-other.md"), "b").unwrap();
+        fs::write(month.join("24-080000-sync.md"), "c").unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2026, 2, 25).unwrap();
+        assert_eq!(
+            entries_titled(dir.path(), date, "sync", "md"),
+            vec![month.join("25-080000-sync.md")]
+        );
     }
 
     #[test]
     fn test_title_with_md_extension() {
-        let result = create_entry_dry_run("test.md", "Content");
+        let result = create_entry_dry_run(&JournalConfig::default(), "test.md", "Content");
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.contains("test.md"));
         assert!(!output.contains("test.md.md")); // Should not double the extension
     }
+
+    fn response(tags: &[&str]) -> LlmResponse {
+        LlmResponse {
+            title: "team-sync.md".to_string(),
+            content: "Discussed Q1 planning.".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            tasks: vec![],
+            model: None,
+            usage: None,
+        }
+    }
+
+    fn split_frontmatter(rendered: &str) -> (serde_yaml::Value, &str) {
+        let rest = rendered.strip_prefix("---\n").unwrap();
+        let (yaml, body) = rest.split_once("---\n").unwrap();
+        (serde_yaml::from_str(yaml).unwrap(), body)
+    }
+
+    #[test]
+    fn test_with_footer_fills_placeholders() {
+        let now = DateTime::parse_from_rfc3339("2026-02-25T18:05:00+01:00").unwrap();
+        assert_eq!(
+            with_footer("Met Jan about Q2\n", Some("— Lisbon, {date} {time}"), &now),
+            "Met Jan about Q2\n\n— Lisbon, 2026-02-25 18:05"
+        );
+        assert_eq!(with_footer("Met Jan", Some("---"), &now), "Met Jan\n\n---");
+    }
+
+    #[test]
+    fn test_with_footer_unset_leaves_content() {
+        let now = DateTime::parse_from_rfc3339("2026-02-25T18:05:00+01:00").unwrap();
+        assert_eq!(with_footer("Met Jan\n", None, &now), "Met Jan\n");
+        assert_eq!(with_footer("Met Jan\n", Some("  "), &now), "Met Jan\n");
+    }
+
+    #[test]
+    fn test_render_with_frontmatter() {
+        let date = DateTime::parse_from_rfc3339("2026-02-25T23:30:00+01:00").unwrap();
+        let rendered = render_with_frontmatter(&response(&["work", "q1"]), "md", &date, None);
+        let (fm, body) = split_frontmatter(&rendered);
+
+        assert_eq!(fm["title"], "team-sync");
+        assert_eq!(fm["date"], "2026-02-25T23:30:00+01:00");
+        assert_eq!(fm["tags"][0], "work");
+        assert_eq!(fm["tags"][1], "q1");
+        assert_eq!(body, "\nDiscussed Q1 planning.");
+    }
+
+    #[test]
+    fn test_render_with_frontmatter_empty_tags() {
+        let date = DateTime::parse_from_rfc3339("2026-02-25T23:30:00Z").unwrap();
+        let rendered = render_with_frontmatter(&response(&[]), "md", &date, None);
+        let (fm, _) = split_frontmatter(&rendered);
+
+        assert!(fm["tags"].as_sequence().unwrap().is_empty());
+    }
+
+    fn metadata() -> GenerationMetadata {
+        let date = DateTime::parse_from_rfc3339("2026-02-25T23:30:00+01:00").unwrap();
+        GenerationMetadata::new("openai", Some("gpt-4o-mini"), Some(0.5), &date)
+    }
+
+    #[test]
+    fn test_render_with_frontmatter_records_metadata() {
+        let date = DateTime::parse_from_rfc3339("2026-02-25T23:30:00+01:00").unwrap();
+        let rendered =
+            render_with_frontmatter(&response(&["work"]), "md", &date, Some(&metadata()));
+        let (fm, body) = split_frontmatter(&rendered);
+
+        assert_eq!(fm["title"], "team-sync");
+        assert_eq!(fm["generated_by"], "openai");
+        assert_eq!(fm["model"], "gpt-4o-mini");
+        assert_eq!(fm["temperature"], 0.5);
+        assert_eq!(fm["generated_at"], "2026-02-25T23:30:00+01:00");
+        assert_eq!(body, "\nDiscussed Q1 planning.");
+
+        let plain = render_with_frontmatter(&response(&["work"]), "md", &date, None);
+        assert!(!plain.contains("generated_by"));
+    }
+
+    #[test]
+    fn test_metadata_footer() {
+        assert_eq!(
+            metadata().footer(),
+            "<!-- generated_by: openai, model: gpt-4o-mini, temperature: 0.5, \
+             generated_at: 2026-02-25T23:30:00+01:00 -->"
+        );
+        let date = DateTime::parse_from_rfc3339("2026-02-25T23:30:00Z").unwrap();
+        assert_eq!(
+            GenerationMetadata::new("none", None, None, &date).footer(),
+            "<!-- generated_by: none, generated_at: 2026-02-25T23:30:00Z -->"
+        );
+    }
+
+    #[test]
+    fn test_unique_title_free() {
+        assert_eq!(unique_title("team-sync", "md", |_| false), "team-sync.md");
+    }
+
+    #[test]
+    fn test_unique_title_successive_suffixes() {
+        let taken = ["team-sync.md", "team-sync-2.md", "team-sync-3.md"];
+        assert_eq!(
+            unique_title("team-sync.md", "md", |t| taken.contains(&t)),
+            "team-sync-4.md"
+        );
+        assert_eq!(
+            unique_title("team-sync.md", "md", |t| t == "team-sync.md"),
+            "team-sync-2.md"
+        );
+    }
+
+    #[test]
+    fn test_title_taken_checks_todays_entries() {
+        let root = tempfile::tempdir().unwrap();
+        let month = root.path().join("2026").join("02");
+        fs::create_dir_all(&month).unwrap();
+        fs::write(month.join("25-220255-team-sync.md"), "").unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2026, 2, 25).unwrap();
+        assert!(title_taken(root.path(), today, "team-sync.md", "md"));
+        assert!(title_taken(root.path(), today, "team-sync", "md"));
+        assert!(!title_taken(root.path(), today, "sync.md", "md"));
+        assert!(!title_taken(root.path(), today, "other.md", "md"));
+        let tomorrow = NaiveDate::from_ymd_opt(2026, 2, 26).unwrap();
+        assert!(!title_taken(root.path(), tomorrow, "team-sync.md", "md"));
+    }
+
+    #[test]
+    fn test_date_prefixed_title() {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 5).unwrap();
+        assert_eq!(
+            date_prefixed_title("team-sync.md", date),
+            "2026-02-05-team-sync.md"
+        );
+    }
+
+    #[test]
+    fn test_date_prefixed_title_does_not_double_prefix() {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 5).unwrap();
+        assert_eq!(
+            date_prefixed_title("2025-12-31-team-sync.md", date),
+            "2025-12-31-team-sync.md"
+        );
+        // Numbers that are not a date still get a prefix
+        assert_eq!(
+            date_prefixed_title("2025-99-99-notes.md", date),
+            "2026-02-05-2025-99-99-notes.md"
+        );
+    }
+
+    #[test]
+    fn test_normalize_content_lf_with_trailing_newline() {
+        let out = normalize_content("Line one \t\r\nLine two\t\n\n\n", true, LineEnding::Lf);
+        assert_eq!(out, "Line one\nLine two\n");
+    }
+
+    #[test]
+    fn test_normalize_content_crlf_without_trailing_newline() {
+        let out = normalize_content("Line one \nLine two\n", false, LineEnding::Crlf);
+        assert_eq!(out, "Line one\r\nLine two");
+    }
+
+    #[test]
+    fn test_normalize_content_keeps_hard_breaks() {
+        let out = normalize_content(
+            "Roses are red,  \r\nviolets blue.   \nDone  \n\nNext\t \n  \n",
+            true,
+            LineEnding::Lf,
+        );
+        // Two or more spaces before a following line become exactly two; at the end of
+        // a paragraph they do nothing and go
+        assert_eq!(out, "Roses are red,  \nviolets blue.  \nDone\n\nNext\n");
+    }
+
+    #[test]
+    fn test_list_entries_skips_todos_and_sorts() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("2026/02")).unwrap();
+        fs::create_dir_all(root.join("2025/12")).unwrap();
+        fs::create_dir_all(root.join("todos")).unwrap();
+        fs::write(root.join("2026/02/25-220255-b.md"), "b").unwrap();
+        fs::write(root.join("2025/12/01-080000-a.md"), "a").unwrap();
+        fs::write(root.join("todos/task.md"), "t").unwrap();
+
+        let entries = list_entries(root, "md").unwrap();
+        let rel: Vec<_> = entries
+            .iter()
+            .map(|p| p.strip_prefix(root).unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(
+            rel,
+            vec!["2025/12/01-080000-a.md", "2026/02/25-220255-b.md"]
+        );
+    }
+
+    #[test]
+    fn test_list_entries_custom_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("2026/02")).unwrap();
+        fs::write(root.join("2026/02/25-220255-notes.txt"), "txt").unwrap();
+        fs::write(root.join("2026/02/25-230000-other.md"), "md").unwrap();
+
+        assert_eq!(
+            list_entries(root, "txt").unwrap(),
+            vec![root.join("2026/02/25-220255-notes.txt")]
+        );
+        let output = "- 2026/02/25-220255-notes.txt\n- 2026/02/25-230000-other.md\n";
+        assert_eq!(
+            parse_list_output(output, root, "txt"),
+            vec![root.join("2026/02/25-220255-notes.txt")]
+        );
+    }
+
+    fn summary(title: &str, tags: &[&str]) -> EntrySummary {
+        EntrySummary {
+            path: PathBuf::from(format!("{}.md", title)),
+            date: None,
+            title: title.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_output() {
+        let output = "Entries in /home/me/journals:\n\
+                      - 2026/02/25-220255-team-sync.md\n\
+                      - 2026/02/26-081500-call-jan.md\n\
+                      /elsewhere/2026/02/27-090000-idea.md\n\
+                      \n\
+                      3 entries\n";
+        let root = Path::new("/home/me/journals");
+        assert_eq!(
+            parse_list_output(output, root, "md"),
+            vec![
+                root.join("2026/02/25-220255-team-sync.md"),
+                root.join("2026/02/26-081500-call-jan.md"),
+                PathBuf::from("/elsewhere/2026/02/27-090000-idea.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entry_summary_reads_name_and_frontmatter() {
+        let root = tempfile::tempdir().unwrap();
+        let month = root.path().join("2026").join("02");
+        fs::create_dir_all(&month).unwrap();
+        let path = month.join("25-220255-team-sync.md");
+        fs::write(
+            &path,
+            "---\ntitle: team-sync\ntags:\n- work\n- q1\n---\n\nNotes.\n",
+        )
+        .unwrap();
+
+        let entry = EntrySummary::from_path(path);
+        assert_eq!(entry.title, "team-sync");
+        assert_eq!(entry.date, NaiveDate::from_ymd_opt(2026, 2, 25));
+        assert_eq!(entry.tags, vec!["work", "q1"]);
+
+        // A missing file or one without frontmatter has no tags
+        let plain = EntrySummary::from_path(month.join("26-081500-call-jan.md"));
+        assert_eq!(plain.title, "call-jan");
+        assert!(plain.tags.is_empty());
+    }
+
+    #[test]
+    fn test_filter_by_tag() {
+        let entries = vec![
+            summary("team-sync", &["work", "q1"]),
+            summary("groceries", &["home"]),
+            summary("review", &["Work"]),
+        ];
+        let titles = |entries: Vec<EntrySummary>| -> Vec<String> {
+            entries.into_iter().map(|e| e.title).collect()
+        };
+        assert_eq!(
+            titles(filter_by_tag(entries.clone(), "work")),
+            vec!["team-sync", "review"]
+        );
+        assert_eq!(
+            titles(filter_by_tag(entries.clone(), "#home")),
+            vec!["groceries"]
+        );
+        assert!(filter_by_tag(entries, "travel").is_empty());
+    }
+
+    #[test]
+    fn test_list_summaries_falls_back_to_scanning() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("2026/02")).unwrap();
+        fs::write(root.path().join("2026/02/25-220255-b.md"), "b").unwrap();
+        let journal = JournalConfig {
+            command: "journal-ai-no-such-backend".to_string(),
+            ..JournalConfig::default()
+        };
+
+        let entries = list_summaries(&journal, root.path()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title, "b");
+    }
 }
@@ -0,0 +1,96 @@
+use anyhow::Result;
+
+/// Keychain service name; each key is stored under its env var name as the account.
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "journal-ai";
+
+/// Whether this build can use the OS keychain.
+pub fn available() -> bool {
+    cfg!(feature = "keyring")
+}
+
+/// The key stored for `account`. Missing entries and platforms without a keychain read
+/// as `None`, so callers fall back to env-only behavior.
+#[cfg(feature = "keyring")]
+pub fn get(account: &str) -> Option<String> {
+    keyring::Entry::new(SERVICE, account)
+        .and_then(|entry| entry.get_password())
+        .ok()
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn get(_account: &str) -> Option<String> {
+    None
+}
+
+#[cfg(feature = "keyring")]
+pub fn set(account: &str, secret: &str) -> Result<()> {
+    use anyhow::Context;
+
+    keyring::Entry::new(SERVICE, account)
+        .and_then(|entry| entry.set_password(secret))
+        .context("Failed to store the key in the OS keychain")
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn set(_account: &str, _secret: &str) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Keychain support is not built in. Rebuild with `--features keyring`"
+    ))
+}
+
+/// Pick an API key: the config file, then the environment, then the keychain.
+/// The keychain is only consulted when nothing else is set.
+pub fn resolve_api_key(
+    configured: Option<String>,
+    env: Option<String>,
+    keychain: impl FnOnce() -> Option<String>,
+) -> Option<String> {
+    configured
+        .or(env)
+        .filter(|key| !key.is_empty())
+        .or_else(keychain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(s: &str) -> Option<String> {
+        Some(s.to_string())
+    }
+
+    #[test]
+    fn test_env_wins_over_keychain() {
+        let resolved = resolve_api_key(None, key("from-env"), || {
+            panic!("keychain should not be read")
+        });
+        assert_eq!(resolved, key("from-env"));
+    }
+
+    #[test]
+    fn test_keychain_when_env_unset() {
+        assert_eq!(
+            resolve_api_key(None, None, || key("from-keychain")),
+            key("from-keychain")
+        );
+        // An empty variable counts as unset
+        assert_eq!(
+            resolve_api_key(None, key(""), || key("from-keychain")),
+            key("from-keychain")
+        );
+    }
+
+    #[test]
+    fn test_no_key_anywhere() {
+        assert_eq!(resolve_api_key(None, None, || None), None);
+    }
+
+    #[test]
+    fn test_config_value_wins() {
+        assert_eq!(
+            resolve_api_key(key("from-config"), key("from-env"), || key("from-keychain")),
+            key("from-config")
+        );
+    }
+}
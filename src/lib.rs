@@ -0,0 +1,745 @@
+//! Generate journal entries from rough notes with an LLM and save them via
+//! file-journal. The `journal-ai` binary is a CLI over this library; other tools can
+//! embed the same pipeline through [`Generator`].
+
+use anyhow::Result;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Print an informational line to stderr unless `quiet` is set, keeping stdout for results.
+#[macro_export]
+macro_rules! progress {
+    ($config:expr, $($arg:tt)*) => {
+        if !$config.quiet {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub mod batch;
+pub mod cache;
+pub mod clock;
+pub mod config;
+pub mod embeddings;
+pub mod error;
+pub mod export;
+pub mod import;
+pub mod input;
+pub mod journal;
+pub mod keychain;
+pub mod pricing;
+pub mod providers;
+pub mod refine;
+pub mod repl;
+pub mod routing;
+pub mod spinner;
+pub mod summarize;
+pub mod tag_index;
+pub mod todos;
+
+use config::Config;
+use providers::{
+    anthropic::AnthropicProvider, ollama::OllamaProvider, openai::OpenAiProvider, sanitize_title,
+    LlmProvider, LlmResponse,
+};
+
+/// Generates and saves entries with one configuration.
+pub struct Generator {
+    config: Config,
+    /// `None` with `provider = "auto"`, which picks a provider per note
+    provider: Option<Box<dyn LlmProvider>>,
+}
+
+impl Generator {
+    /// Build the configured provider, failing when it is not reachable.
+    pub async fn new(config: Config) -> Result<Self> {
+        let provider = match config.provider.as_str() {
+            "auto" => None,
+            _ => Some(build_provider(&config).await?),
+        };
+        Ok(Self { config, provider })
+    }
+
+    /// Use `provider` instead of the configured one, e.g. `providers::mock::MockProvider`.
+    pub fn with_provider(config: Config, provider: Box<dyn LlmProvider>) -> Self {
+        Self {
+            config,
+            provider: Some(provider),
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Turn `content` into an entry with the configured title rules applied.
+    pub async fn generate(&self, content: &str) -> Result<LlmResponse> {
+        match &self.provider {
+            Some(provider) => generate_entry(provider.as_ref(), &self.config, content).await,
+            None => {
+                let config = route_provider(&self.config, Some(content.chars().count()));
+                let provider = build_provider(&config).await?;
+                generate_entry(provider.as_ref(), &config, content).await
+            }
+        }
+    }
+
+    /// Save `response` as a new entry (or into today's file in `daily_mode`) and write
+    /// its todos. Returns the journal's output, e.g. "Created journal entry: <path>".
+    pub fn save(&self, response: &LlmResponse) -> Result<String> {
+        let daily = match self.config.daily_mode {
+            true => Some(daily_entry_path(&self.config)?),
+            false => None,
+        };
+        Ok(write_response(&self.config, response, daily.as_deref())?.result)
+    }
+}
+
+/// `config` with `provider = "auto"` resolved for a note of `len` characters; `None`
+/// (no single note) picks the provider for long notes.
+pub fn route_provider(config: &Config, len: Option<usize>) -> Config {
+    let mut config = config.clone();
+    if config.provider == "auto" {
+        config.provider = routing::choose_provider(len.unwrap_or(usize::MAX), &config);
+    }
+    config
+}
+
+/// Today's file for `daily_mode`, dated in the configured timezone.
+pub fn daily_entry_path(config: &Config) -> Result<PathBuf> {
+    let today = clock::now(config.use_utc).date_naive();
+    let journal_root = journal::journal_root(&config.journal)?;
+    Ok(journal::daily_path(
+        &journal_root,
+        today,
+        &config.journal.extension,
+    ))
+}
+
+/// Generate an entry and apply the configured title rules (sanitizing, date prefix).
+/// An entry reused from the cache cost nothing, so its `usage` is `None`.
+pub async fn generate_entry(
+    provider: &dyn LlmProvider,
+    config: &Config,
+    content: &str,
+) -> Result<LlmResponse> {
+    let options = config.prompt_options()?;
+    let cache = open_cache(config);
+    let key = cache::Cache::key(&request_fingerprint(provider, config, &options, content));
+    let mut response = match cache.as_ref().and_then(|cache| cache.get(&key)) {
+        Some(cached) => {
+            progress!(config, "Reusing the earlier response (cached)");
+            cached
+        }
+        None => {
+            let response = generate_in_language(provider, config, content, options).await?;
+            if let Some(cache) = &cache {
+                if let Err(e) = cache.put(&key, &response) {
+                    eprintln!("Warning: {:#}", e);
+                }
+            }
+            response
+        }
+    };
+    if config.cleanup == providers::Cleanup::Off {
+        // The model only titles and tags the note; its text is kept as written
+        response.content = content.trim().to_string();
+    }
+    if let Some(max_words) = config.max_words {
+        response.content = providers::truncate_words(&response.content, max_words);
+    }
+    response.tags = allowed_tags(config, response.tags)?;
+    response.title = entry_title(config, &response.title);
+    Ok(response)
+}
+
+/// Everything that changes the model's answer to `content`: the provider's settings
+/// (model, URL, temperature, stop sequences, extra options), the prompt text it would
+/// send, and the prompt options for providers that don't expose their prompt.
+fn request_fingerprint(
+    provider: &dyn LlmProvider,
+    config: &Config,
+    options: &providers::PromptOptions,
+    content: &str,
+) -> serde_json::Value {
+    serde_json::json!({
+        "provider": config.provider,
+        "settings": config.provider_settings(),
+        "prompt": provider.prompt_text(content, options),
+        // The system prompt the provider will actually use
+        "system": options.system_or(config.system_prompt()),
+        "instructions": options.extra_instructions(),
+        "max_tags": options.max_tags,
+        "input": content,
+    })
+}
+
+/// `tags` limited to `allowed_tags` (in the allowlist's spelling), failing when
+/// `require_tag` is set and none is left. `--tag` tags are added later, unfiltered.
+pub fn allowed_tags(config: &Config, tags: Vec<String>) -> Result<Vec<String>> {
+    let tags = if config.allowed_tags.is_empty() {
+        tags
+    } else {
+        let mut kept: Vec<String> = vec![];
+        for tag in &tags {
+            let tag = tag.to_lowercase();
+            let allowed = config
+                .allowed_tags
+                .iter()
+                .find(|allowed| allowed.to_lowercase() == tag);
+            if let Some(allowed) = allowed.filter(|allowed| !kept.contains(allowed)) {
+                kept.push(allowed.clone());
+            }
+        }
+        kept
+    };
+    if config.require_tag && tags.is_empty() {
+        return Err(match config.allowed_tags.is_empty() {
+            true => anyhow::anyhow!("The model returned no tags and require_tag is set"),
+            false => anyhow::anyhow!(
+                "None of the generated tags is in allowed_tags ({}) and require_tag is set",
+                config.allowed_tags.join(", ")
+            ),
+        });
+    }
+    Ok(tags)
+}
+
+/// `title` with the configured title rules applied (sanitizing, date prefix).
+pub fn entry_title(config: &Config, title: &str) -> String {
+    let title = sanitize_title(title, &config.title_options());
+    if config.date_prefix {
+        let today = clock::now(config.use_utc).date_naive();
+        return journal::date_prefixed_title(&title, today);
+    }
+    title
+}
+
+/// The response cache, when enabled. Raw mode never calls a model, so it isn't cached.
+fn open_cache(config: &Config) -> Option<cache::Cache> {
+    if !config.cache || config.provider == "none" {
+        return None;
+    }
+    let dir = cache::Cache::default_dir().ok()?;
+    Some(cache::Cache::new(
+        dir,
+        std::time::Duration::from_secs(config.cache_ttl_secs),
+    ))
+}
+
+/// Ask the provider for an entry; with `enforce_language`, retry once when the model
+/// answers in another language than the note.
+async fn generate_in_language(
+    provider: &dyn LlmProvider,
+    config: &Config,
+    content: &str,
+    mut options: providers::PromptOptions,
+) -> Result<LlmResponse> {
+    let mut response = provider.generate(content, &options).await?;
+    if config.enforce_language {
+        if let Some((expected, _)) = providers::language_mismatch(content, &response.content) {
+            progress!(
+                config,
+                "The model changed the language, retrying in {}...",
+                expected.eng_name()
+            );
+            options.language = Some(expected.eng_name().to_string());
+            let first_usage = response.usage;
+            response = provider.generate(content, &options).await?;
+            // Both attempts were paid for
+            response.usage = match (first_usage, response.usage) {
+                (Some(first), Some(retry)) => Some(first + retry),
+                (first, retry) => first.or(retry),
+            };
+            if let Some((expected, actual)) =
+                providers::language_mismatch(content, &response.content)
+            {
+                return Err(anyhow::anyhow!(
+                    "The model translated the note from {} to {}. Try another model, or set enforce_language = false",
+                    expected.eng_name(),
+                    actual.eng_name()
+                ));
+            }
+        }
+    }
+    Ok(response)
+}
+
+/// Create the configured provider without contacting it (e.g. for `--print-prompt`).
+pub fn create_provider(config: &Config) -> Result<Box<dyn LlmProvider>> {
+    let provider: Box<dyn LlmProvider> = match config.provider.as_str() {
+        "ollama" => Box::new(OllamaProvider::new(
+            config.ollama.clone(),
+            config.retry.clone(),
+        )?),
+        "openai" => Box::new(OpenAiProvider::new(
+            config.openai.clone(),
+            config.retry.clone(),
+        )?),
+        "anthropic" => Box::new(AnthropicProvider::new(
+            config.anthropic.clone(),
+            config.retry.clone(),
+        )?),
+        "none" => Box::new(providers::raw::RawProvider::new(config.title_options())),
+        #[cfg(feature = "mock")]
+        "mock" => Box::new(providers::mock::MockProvider::new()),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unknown provider: {}. Use 'ollama', 'openai', 'anthropic' or 'none'",
+                config.provider
+            ));
+        }
+    };
+    Ok(provider)
+}
+
+/// Create the configured provider, warning or failing when it is not reachable.
+pub async fn build_provider(config: &Config) -> Result<Box<dyn LlmProvider>> {
+    let provider = create_provider(config)?;
+    check_provider(config, provider.as_ref()).await?;
+    Ok(provider)
+}
+
+/// With `precheck` on, warn when Ollama looks down and fail when a cloud provider is
+/// not set up. With it off, nothing is asked of the provider.
+pub async fn check_provider(config: &Config, provider: &dyn LlmProvider) -> Result<()> {
+    if !config.precheck {
+        return Ok(());
+    }
+    match config.provider.as_str() {
+        "ollama" if !provider.is_available().await => {
+            eprintln!(
+                "Warning: Ollama does not appear to be available at {}",
+                config.ollama.base_url
+            );
+            eprintln!("Make sure Ollama is running: ollama serve");
+            eprintln!("Attempting anyway...");
+        }
+        "openai" if !provider.is_available().await => {
+            return Err(anyhow::anyhow!(
+                "OpenAI provider not available. Make sure OPENAI_API_KEY is set."
+            ));
+        }
+        "anthropic" if !provider.is_available().await => {
+            return Err(anyhow::anyhow!(
+                "Anthropic provider not available. Make sure ANTHROPIC_API_KEY is set."
+            ));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn print_preview(response: &LlmResponse) {
+    print!("{}", format_preview(response));
+}
+
+pub fn format_preview(response: &LlmResponse) -> String {
+    format_rendered_preview(response, false)
+}
+
+/// The preview, with the content styled as terminal markdown when `render` is set.
+pub fn format_rendered_preview(response: &LlmResponse, render: bool) -> String {
+    format!("\n=== Preview ===\n{}", format_entry(response, render))
+}
+
+/// `markdown` styled for the terminal (headers, bullets, bold), or as is when
+/// `render` is off.
+pub fn render_markdown(markdown: &str, render: bool) -> String {
+    if !render {
+        return markdown.to_string();
+    }
+    termimad::MadSkin::default().term_text(markdown).to_string()
+}
+
+/// Title, content, word count, tags and tasks of an entry, one field per line.
+pub fn format_entry(response: &LlmResponse, render: bool) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("Title: {}\n", response.title));
+    if render {
+        out.push_str(&format!(
+            "Content:\n{}\n",
+            render_markdown(&response.content, true)
+        ));
+    } else {
+        out.push_str(&format!("Content: {}\n", response.content));
+    }
+    out.push_str(&format!(
+        "Words: {}\n",
+        providers::word_count(&response.content)
+    ));
+    if !response.tags.is_empty() {
+        out.push_str(&format!("Tags: {}\n", response.tags.join(", ")));
+    }
+    if !response.tasks.is_empty() {
+        out.push_str("Tasks:\n");
+        for task in &response.tasks {
+            let due = task.due.as_deref().unwrap_or("no due date");
+            out.push_str(&format!(
+                "  - [{}] {} ({})\n",
+                task.priority, task.text, due
+            ));
+        }
+    }
+    out
+}
+
+/// What was written to disk for a single generated entry.
+pub struct SavedEntry {
+    /// Output of the file-journal command
+    pub result: String,
+    /// Absolute path reported by file-journal, if it could be parsed
+    pub path: Option<PathBuf>,
+    pub todos: Vec<PathBuf>,
+}
+
+/// Save a generated entry via file-journal and write its todo files, reporting progress.
+///
+/// The file-journal result goes to `out`.
+pub fn save_response(
+    config: &Config,
+    response: &LlmResponse,
+    append: Option<&std::path::Path>,
+    out: &mut dyn Write,
+) -> Result<SavedEntry> {
+    match append {
+        Some(file) => progress!(config, "Appending to entry: {}", file.display()),
+        None => progress!(
+            config,
+            "Saving entry: {} ({} words)",
+            response.title,
+            providers::word_count(&response.content)
+        ),
+    }
+
+    let saved = write_response(config, response, append)?;
+    writeln!(out, "{}", saved.result)?;
+    if !saved.todos.is_empty() {
+        progress!(config, "Created {} todo(s)", saved.todos.len());
+    }
+
+    Ok(saved)
+}
+
+/// Apply `on_conflict` when today's journal already has an entry titled `title`.
+fn resolve_title_conflict(
+    config: &Config,
+    title: &str,
+    now: &chrono::DateTime<chrono::FixedOffset>,
+) -> Result<String> {
+    if config.on_conflict == config::OnConflict::Overwrite {
+        return Ok(title.to_string());
+    }
+    let journal_root = journal::journal_root(&config.journal)?;
+    let extension = &config.journal.extension;
+    let taken = |t: &str| journal::title_taken(&journal_root, now.date_naive(), t, extension);
+    if !taken(title) {
+        return Ok(title.to_string());
+    }
+    match config.on_conflict {
+        config::OnConflict::Error => Err(anyhow::anyhow!(
+            "An entry titled {} already exists today. Pass --on-conflict suffix or overwrite",
+            title
+        )),
+        _ => {
+            let unique = journal::unique_title(title, extension, taken);
+            progress!(
+                config,
+                "An entry titled {} exists; saving as {}",
+                title,
+                unique
+            );
+            Ok(unique)
+        }
+    }
+}
+
+/// Save a generated entry via file-journal, or append it to `append`, and write its
+/// todo files without printing.
+///
+/// In append mode the generated title is ignored and tags go into the entry's frontmatter.
+pub fn write_response(
+    config: &Config,
+    response: &LlmResponse,
+    append: Option<&std::path::Path>,
+) -> Result<SavedEntry> {
+    let now = clock::now(config.use_utc);
+    let footer = config.footer.as_deref();
+    let result = match append {
+        Some(file) if config.daily_mode => {
+            if config.backup && file.exists() {
+                journal::backup_entry(file)?;
+            }
+            let content = journal::with_footer(&response.content, footer, &now);
+            journal::append_daily(file, &content, &response.tags, &now)?
+        }
+        Some(file) => {
+            if config.backup {
+                journal::backup_entry(file)?;
+            }
+            let content = journal::with_footer(&response.content, footer, &now);
+            journal::append_entry(file, &content, &response.tags, &now)?
+        }
+        None => {
+            let title = resolve_title_conflict(config, &response.title, &now)?;
+            let renamed;
+            let response = if title == response.title {
+                response
+            } else {
+                renamed = LlmResponse {
+                    title,
+                    ..response.clone()
+                };
+                &renamed
+            };
+            let metadata = config.record_metadata.then(|| {
+                journal::GenerationMetadata::new(
+                    &config.provider,
+                    response.model.as_deref().or(config.model()),
+                    config.temperature(),
+                    &now,
+                )
+            });
+            let content = match (config.frontmatter, &metadata) {
+                (true, _) => journal::render_with_frontmatter(
+                    response,
+                    &config.journal.extension,
+                    &now,
+                    metadata.as_ref(),
+                ),
+                (false, Some(metadata)) => {
+                    format!("{}\n\n{}", response.content.trim_end(), metadata.footer())
+                }
+                (false, None) => response.content.clone(),
+            };
+            let content = journal::with_footer(&content, footer, &now);
+            let content =
+                journal::normalize_content(&content, config.trailing_newline, config.line_ending);
+            journal::create_entry(&config.journal, &response.title, &content)?
+        }
+    };
+
+    // Derive created path from file-journal output
+    // Expected output: "Created journal entry: /path/to/journals/YYYY/MM/dd-HHMMSS-title.md"
+    // (append_entry and append_daily report "Appended to journal entry: ..." in the same shape)
+    let created_path = result.split(": ").last().unwrap_or("").trim();
+
+    let mut saved = SavedEntry {
+        path: (!created_path.is_empty()).then(|| PathBuf::from(created_path)),
+        todos: vec![],
+        result: String::new(),
+    };
+
+    // Create todo files (best effort)
+    if !response.tasks.is_empty() {
+        let journal_root = journal::journal_root(&config.journal)?;
+
+        let linked_note = if !created_path.is_empty() {
+            let jp = journal_root.to_string_lossy();
+            created_path
+                .strip_prefix(&format!("{}/", jp.trim_end_matches('/')))
+                .unwrap_or(created_path)
+                .to_string()
+        } else {
+            // fallback to just title (no date path)
+            response.title.clone()
+        };
+
+        saved.todos =
+            todos::save_todos(&journal_root, &linked_note, &response.tasks, config.use_utc)?;
+    }
+
+    if config.tag_index && !response.tags.is_empty() {
+        if let Some(path) = &saved.path {
+            if let Err(e) = index_tags(config, path, &response.tags) {
+                eprintln!("Warning: {:#}", e);
+            }
+        }
+    }
+
+    saved.result = result;
+    Ok(saved)
+}
+
+/// Add a saved entry's tags to the tag index, keyed by its path under the journal root.
+fn index_tags(config: &Config, path: &std::path::Path, tags: &[String]) -> Result<()> {
+    let journal_root = journal::journal_root(&config.journal)?;
+    let filename = path.strip_prefix(&journal_root).unwrap_or(path);
+    tag_index::TagIndex::new(tag_index::TagIndex::default_path()?)
+        .add(&filename.to_string_lossy(), tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_generate_then_preview_with_mock() {
+        let provider = providers::mock::MockProvider::new();
+        let config = Config {
+            date_prefix: true,
+            ..Config::default()
+        };
+
+        let response = generate_entry(&provider, &config, "Call Jan about Q2 budget")
+            .await
+            .unwrap();
+        let today = clock::now(config.use_utc).date_naive();
+        assert_eq!(
+            response.title,
+            format!("{}-call-jan-about-q2.md", today.format("%Y-%m-%d"))
+        );
+        assert_eq!(provider.prompts(), vec!["Call Jan about Q2 budget"]);
+
+        let preview = format_preview(&response);
+        assert!(preview.contains(&format!("Title: {}", response.title)));
+        assert!(preview.contains("Content: Call Jan about Q2 budget"));
+        assert!(preview.contains("Tags: mock"));
+        assert!(preview.contains("Words: 5"));
+
+        let dry_run =
+            journal::create_entry_dry_run(&config.journal, &response.title, &response.content)
+                .unwrap();
+        assert!(dry_run.contains(&response.title));
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let markdown = "# Standup\n\n- shipped the **parser**\n- fixed CI\n";
+        assert_eq!(render_markdown(markdown, false), markdown);
+
+        let styled = render_markdown(markdown, true);
+        assert!(styled.contains('\x1b'));
+        assert!(styled.contains("Standup"));
+        assert!(styled.contains("parser"));
+        assert!(!styled.contains("**parser**"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_truncates_to_max_words() {
+        let provider = providers::mock::MockProvider::new();
+        let config = Config {
+            max_words: Some(6),
+            ..Config::default()
+        };
+
+        let response = generate_entry(&provider, &config, "Call Jan. Ask about the Q2 budget.")
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Call Jan.");
+        assert!(provider.prompts()[0].contains("Call Jan. Ask about the Q2 budget."));
+    }
+
+    #[test]
+    fn test_allowed_tags_filter_and_require() {
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let mut config = Config {
+            allowed_tags: tags(&["Work", "health", "q2"]),
+            ..Config::default()
+        };
+        assert_eq!(
+            allowed_tags(&config, tags(&["work", "coffee", "Q2", "WORK"])).unwrap(),
+            ["Work", "q2"]
+        );
+        assert!(allowed_tags(&config, tags(&["coffee"])).unwrap().is_empty());
+
+        config.require_tag = true;
+        let err = allowed_tags(&config, tags(&["coffee"]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("allowed_tags (Work, health, q2)"), "{}", err);
+        assert_eq!(
+            allowed_tags(&config, tags(&["health"])).unwrap(),
+            ["health"]
+        );
+
+        // Without an allowlist, require_tag only asks for any tag
+        config.allowed_tags.clear();
+        assert!(allowed_tags(&config, vec![]).is_err());
+        assert_eq!(
+            allowed_tags(&config, tags(&["coffee"])).unwrap(),
+            ["coffee"]
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_covers_generation_settings() {
+        let fingerprint = |config: &Config| {
+            let provider = create_provider(config).unwrap();
+            let options = config.prompt_options().unwrap();
+            request_fingerprint(provider.as_ref(), config, &options, "Call Jan")
+        };
+        let config = Config::default();
+        let base = fingerprint(&config);
+        assert_eq!(base, fingerprint(&config.clone()));
+
+        let mut changed = config.clone();
+        changed.ollama.temperature = Some(0.9);
+        assert_ne!(base, fingerprint(&changed));
+        let mut changed = config.clone();
+        changed.ollama.base_url = "http://gpu-box:11434".to_string();
+        assert_ne!(base, fingerprint(&changed));
+        let mut changed = config.clone();
+        changed.ollama.stop = vec!["###".to_string()];
+        assert_ne!(base, fingerprint(&changed));
+        let mut changed = config.clone();
+        changed
+            .ollama
+            .extra_options
+            .insert("top_p".to_string(), serde_json::json!(0.5));
+        assert_ne!(base, fingerprint(&changed));
+        let mut changed = config.clone();
+        changed.max_tags = 2;
+        assert_ne!(base, fingerprint(&changed));
+    }
+
+    #[tokio::test]
+    async fn test_precheck_off_skips_availability() {
+        let provider = providers::mock::MockProvider::new();
+        let mut config = Config::default();
+        assert!(config.precheck);
+
+        check_provider(&config, &provider).await.unwrap();
+        assert_eq!(provider.availability_checks(), 1);
+
+        config.precheck = false;
+        check_provider(&config, &provider).await.unwrap();
+        generate_entry(&provider, &config, "Call Jan about Q2 budget")
+            .await
+            .unwrap();
+        assert_eq!(provider.availability_checks(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_generator_generates_and_saves() {
+        let journal_dir = tempfile::tempdir().unwrap();
+        let mut config = Config {
+            quiet: true,
+            daily_mode: true,
+            ..Config::default()
+        };
+        config.journal.journal_dir = Some(journal_dir.path().to_path_buf());
+        let generator =
+            Generator::with_provider(config, Box::new(providers::mock::MockProvider::new()));
+
+        let first = generator
+            .generate("Call Jan about Q2 budget")
+            .await
+            .unwrap();
+        assert_eq!(first.title, "call-jan-about-q2.md");
+        let result = generator.save(&first).unwrap();
+        let today = clock::now(generator.config().use_utc).date_naive();
+        let file = journal::daily_path(journal_dir.path(), today, "md");
+        assert_eq!(result, format!("Created journal entry: {}", file.display()));
+
+        let second = generator.generate("Jan sent the numbers").await.unwrap();
+        assert!(generator
+            .save(&second)
+            .unwrap()
+            .starts_with("Appended to journal entry: "));
+        let text = std::fs::read_to_string(&file).unwrap();
+        assert!(text.starts_with("---\ntags:\n- mock\n---\n"));
+        let first_at = text.find("Call Jan about Q2 budget").unwrap();
+        assert!(first_at < text.find("Jan sent the numbers").unwrap());
+    }
+}
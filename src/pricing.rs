@@ -0,0 +1,92 @@
+use crate::providers::Usage;
+
+/// USD price per million tokens for a model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+/// OpenAI list prices as (model prefix, input, output), so dated snapshots
+/// (`gpt-4o-mini-2024-07-18`) resolve to their family.
+const OPENAI_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-4", 30.00, 60.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("o3-mini", 1.10, 4.40),
+];
+
+/// Price for a model, matching the longest known prefix; `None` for unknown models.
+pub fn price(model: &str) -> Option<ModelPrice> {
+    OPENAI_PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|&(_, input, output)| ModelPrice { input, output })
+}
+
+/// Estimated USD cost of a request, if the model's price is known.
+pub fn estimate_cost(model: &str, usage: &Usage) -> Option<f64> {
+    let price = price(model)?;
+    Some(
+        (usage.prompt_tokens as f64 * price.input + usage.completion_tokens as f64 * price.output)
+            / 1_000_000.0,
+    )
+}
+
+/// One-line usage report, e.g. `Tokens: 120 prompt + 45 completion (~$0.000045)`.
+pub fn describe(model: &str, usage: &Usage) -> String {
+    let tokens = format!(
+        "Tokens: {} prompt + {} completion",
+        usage.prompt_tokens, usage.completion_tokens
+    );
+    match estimate_cost(model, usage) {
+        Some(cost) => format!("{} (~${:.6})", tokens, cost),
+        None => tokens,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+        }
+    }
+
+    #[test]
+    fn test_price_matches_longest_prefix() {
+        assert_eq!(price("gpt-4o").unwrap().input, 2.50);
+        assert_eq!(price("gpt-4o-mini").unwrap().input, 0.15);
+        assert_eq!(price("gpt-4o-mini-2024-07-18").unwrap().input, 0.15);
+        assert_eq!(price("gpt-4").unwrap().input, 30.00);
+        assert!(price("llama3.2").is_none());
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let cost = estimate_cost("gpt-4o-mini", &usage(1_000_000, 1_000_000)).unwrap();
+        assert!((cost - 0.75).abs() < 1e-9);
+        assert!(estimate_cost("my-finetune", &usage(100, 100)).is_none());
+    }
+
+    #[test]
+    fn test_describe_unknown_model_has_no_cost() {
+        assert_eq!(
+            describe("my-finetune", &usage(120, 45)),
+            "Tokens: 120 prompt + 45 completion"
+        );
+        assert_eq!(
+            describe("gpt-4o-mini", &usage(1000, 1000)),
+            "Tokens: 1000 prompt + 1000 completion (~$0.000750)"
+        );
+    }
+}
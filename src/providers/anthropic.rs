@@ -0,0 +1,387 @@
+use crate::config::{AnthropicConfig, RetryConfig};
+use crate::providers::{
+    build_tags_prompt, capture, check_status, checked_base_url, cleanup_instructions,
+    explain_timeout, format_messages, generate_parsed, is_example_title, json_shape, log_request,
+    normalize_tags, parse_model_ids, protect_code_blocks, restore_code_blocks, shared_client,
+    tags_from_response, title_from_content, unset_extra_options, with_retry, Connectivity,
+    LlmProvider, LlmResponse, PromptOptions, RateLimiter, CHECK_TIMEOUT, DEFAULT_SYSTEM_PROMPT,
+    SUMMARIZE_SYSTEM_PROMPT, TAGS_SYSTEM_PROMPT,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const MAX_TOKENS: u32 = 4096;
+
+pub struct AnthropicProvider {
+    config: AnthropicConfig,
+    retry: RetryConfig,
+    limiter: RateLimiter,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicRequest {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    system: String,
+    messages: Vec<Message>,
+    /// `extra_options` the fields above leave unset
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(default)]
+    text: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(mut config: AnthropicConfig, retry: RetryConfig) -> Result<Self> {
+        config.base_url = checked_base_url("anthropic", &config.base_url)?;
+        if config.api_key.is_none() {
+            return Err(anyhow!("Anthropic API key not configured. Set ANTHROPIC_API_KEY environment variable or add to config"));
+        }
+
+        let client = shared_client(
+            Duration::from_secs(config.timeout_secs),
+            config.proxy.as_deref(),
+        )?;
+        Ok(Self {
+            config,
+            limiter: RateLimiter::per_minute(retry.rate_limit_per_min),
+            retry,
+            client,
+        })
+    }
+
+    /// Plain-text request with the same temperature and token cap as generation.
+    fn build_summarize_request(&self, prompt: &str) -> AnthropicRequest {
+        self.build_text_request(SUMMARIZE_SYSTEM_PROMPT, prompt)
+    }
+
+    fn build_tags_request(&self, content: &str, max_tags: usize) -> AnthropicRequest {
+        self.build_text_request(TAGS_SYSTEM_PROMPT, &build_tags_prompt(content, max_tags))
+    }
+
+    /// Plain-text (not JSON mode) request for `prompt` under `system`.
+    fn build_text_request(&self, system: &str, prompt: &str) -> AnthropicRequest {
+        self.with_extra_options(AnthropicRequest {
+            model: self.config.model.clone(),
+            max_tokens: self.config.max_tokens.unwrap_or(MAX_TOKENS),
+            temperature: Some(self.config.temperature),
+            system: system.to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: prompt.to_string(),
+            }],
+            extra: serde_json::Map::new(),
+        })
+    }
+
+    /// `request` with the configured `extra_options` it doesn't set itself.
+    fn with_extra_options(&self, mut request: AnthropicRequest) -> AnthropicRequest {
+        request.extra = unset_extra_options(&request, &self.config.extra_options);
+        request
+    }
+
+    fn build_request(&self, user_input: &str, options: &PromptOptions) -> AnthropicRequest {
+        let system = options
+            .system_or(self.config.system_prompt.as_deref())
+            .unwrap_or(DEFAULT_SYSTEM_PROMPT);
+
+        self.with_extra_options(AnthropicRequest {
+            model: self.config.model.clone(),
+            max_tokens: self.config.max_tokens.unwrap_or(MAX_TOKENS),
+            temperature: Some(self.config.temperature),
+            system: system.to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: format!(
+                    "Process this journal note and return structured JSON.\n\nInput: {input}\n\n{fields}\n\n{shape}\n{extra}",
+                    input = user_input,
+                    fields = cleanup_instructions(options),
+                    shape = json_shape(),
+                    extra = options.extra_instructions()
+                ),
+            }],
+            extra: serde_json::Map::new(),
+        })
+    }
+
+    /// Lightweight authenticated GET /models to confirm the key works.
+    pub async fn check_connection(&self) -> Result<Connectivity> {
+        let api_key = self
+            .config
+            .api_key
+            .as_ref()
+            .ok_or_else(|| anyhow!("Anthropic API key not set"))?;
+        let response = self
+            .client
+            .get(format!("{}/models", self.config.base_url))
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .timeout(CHECK_TIMEOUT)
+            .send()
+            .await
+            .context("Failed to connect to Anthropic API")?;
+        Ok(Connectivity::from_status(response.status()))
+    }
+
+    /// IDs of the models the key can use, from GET /models.
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let api_key = self
+            .config
+            .api_key
+            .as_ref()
+            .ok_or_else(|| anyhow!("Anthropic API key not set"))?;
+        let response = self
+            .client
+            .get(format!("{}/models", self.config.base_url))
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .timeout(CHECK_TIMEOUT)
+            .send()
+            .await
+            .context("Failed to connect to Anthropic API")?;
+        let body = check_status("Anthropic", response).await?.text().await?;
+        parse_model_ids("Anthropic", &body)
+    }
+
+    /// POST a request to the Messages API, retrying transient failures, and return the
+    /// concatenated text blocks.
+    async fn send(&self, request: &AnthropicRequest) -> Result<String> {
+        let api_key = self
+            .config
+            .api_key
+            .as_ref()
+            .ok_or_else(|| anyhow!("Anthropic API key not set"))?;
+
+        let url = format!("{}/messages", self.config.base_url);
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        log_request("Anthropic", &url, &request.model, request, Some(api_key));
+
+        let response = with_retry(&self.retry, || async {
+            self.limiter.wait().await;
+            let request = self
+                .client
+                .post(&url)
+                .header("x-api-key", api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .header("Content-Type", "application/json")
+                .json(request);
+            let response = capture::send("Anthropic", request)
+                .await
+                .context("Failed to connect to Anthropic API")?;
+            check_status("Anthropic", response).await
+        })
+        .await
+        .map_err(|e| explain_timeout(e, timeout))?;
+
+        let anthropic_resp: AnthropicResponse = response
+            .json()
+            .await
+            .context("Failed to parse Anthropic response")
+            .map_err(|e| explain_timeout(e, timeout))?;
+
+        let text: String = anthropic_resp
+            .content
+            .iter()
+            .filter(|b| b.type_ == "text")
+            .map(|b| b.text.as_str())
+            .collect();
+
+        if text.trim().is_empty() {
+            return Err(anyhow!("No response from Anthropic"));
+        }
+
+        Ok(text)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    fn prompt_text(&self, prompt: &str, options: &PromptOptions) -> Option<String> {
+        let (protected, _) = protect_code_blocks(prompt);
+        let request = self.build_request(&protected, options);
+        let system = std::iter::once(("system", request.system.as_str()));
+        let messages = request
+            .messages
+            .iter()
+            .map(|message| (message.role.as_str(), message.content.as_str()));
+        Some(format_messages(system.chain(messages)))
+    }
+
+    async fn generate(&self, prompt: &str, options: &PromptOptions) -> Result<LlmResponse> {
+        // Code blocks are swapped for placeholders so the cleanup can't reflow them
+        let (protected, code_blocks) = protect_code_blocks(prompt);
+        let protected = &protected;
+        let mut llm_response =
+            generate_parsed(self.retry.json_retries, options, |options| async move {
+                self.send(&self.build_request(protected, &options)).await
+            })
+            .await?;
+        llm_response.content = restore_code_blocks(&llm_response.content, &code_blocks);
+
+        // Guard against the model returning the literal example placeholder
+        let raw_title = &llm_response.title;
+        let title = if raw_title.trim().is_empty() {
+            title_from_content(&llm_response.content)
+        } else if is_example_title(raw_title) {
+            let words: Vec<&str> = prompt.split_whitespace().take(5).collect();
+            words.join(" ")
+        } else {
+            raw_title.clone()
+        };
+
+        Ok(LlmResponse {
+            title,
+            tags: options.limit_tags(normalize_tags(llm_response.tags)),
+            ..llm_response
+        })
+    }
+
+    async fn summarize(&self, prompt: &str) -> Result<String> {
+        self.send(&self.build_summarize_request(prompt)).await
+    }
+
+    async fn suggest_tags(&self, content: &str, max_tags: usize) -> Result<Vec<String>> {
+        let raw = self
+            .send(&self.build_tags_request(content, max_tags))
+            .await?;
+        tags_from_response(&raw, max_tags)
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.api_key.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> AnthropicProvider {
+        AnthropicProvider::new(
+            AnthropicConfig {
+                api_key: Some("test-key".to_string()),
+                ..AnthropicConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_new_requires_api_key() {
+        assert!(
+            AnthropicProvider::new(AnthropicConfig::default(), RetryConfig::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_summarize_request_uses_configured_temperature_and_max_tokens() {
+        let provider = AnthropicProvider::new(
+            AnthropicConfig {
+                api_key: Some("test-key".to_string()),
+                temperature: 0.8,
+                max_tokens: Some(300),
+                ..AnthropicConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let body = serde_json::to_value(provider.build_summarize_request("entries")).unwrap();
+
+        assert_eq!(body["temperature"].as_f64().unwrap() as f32, 0.8);
+        assert_eq!(body["max_tokens"], 300);
+        assert_eq!(body["system"], SUMMARIZE_SYSTEM_PROMPT);
+    }
+
+    #[test]
+    fn test_build_request() {
+        let request = provider().build_request("Test input", &PromptOptions::default());
+        assert_eq!(request.model, "claude-3-5-sonnet-latest");
+        assert_eq!(request.messages.len(), 1);
+        assert_eq!(request.messages[0].role, "user");
+        assert!(request.messages[0].content.contains("Test input"));
+        assert!(request.system.contains("NEVER translate"));
+    }
+
+    #[test]
+    fn test_request_mentions_tag_count() {
+        let options = PromptOptions {
+            max_tags: 2,
+            ..PromptOptions::default()
+        };
+        let request = provider().build_request("Test", &options);
+        assert!(request.messages[0]
+            .content
+            .contains("\"tags\": 0-2 relevant keywords"));
+    }
+
+    #[test]
+    fn test_custom_system_prompt() {
+        let request = provider().build_request(
+            "Test",
+            &PromptOptions {
+                system: Some("Custom prompt".to_string()),
+                ..PromptOptions::default()
+            },
+        );
+        assert_eq!(request.system, "Custom prompt");
+    }
+
+    #[test]
+    fn test_system_prompt_precedence() {
+        let configured = AnthropicProvider::new(
+            AnthropicConfig {
+                api_key: Some("test-key".to_string()),
+                system_prompt: Some("Answer tersely.".to_string()),
+                ..AnthropicConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let per_request = PromptOptions {
+            system: Some("Custom prompt".to_string()),
+            ..PromptOptions::default()
+        };
+
+        // The request's prompt beats the config, which beats the built-in
+        assert_eq!(
+            configured.build_request("Test", &per_request).system,
+            "Custom prompt"
+        );
+        assert_eq!(
+            configured
+                .build_request("Test", &PromptOptions::default())
+                .system,
+            "Answer tersely."
+        );
+        assert_eq!(
+            provider()
+                .build_request("Test", &PromptOptions::default())
+                .system,
+            DEFAULT_SYSTEM_PROMPT
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::providers::{LlmProvider, LlmResponse, PromptOptions};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Offline provider that derives a deterministic response from the input.
+///
+/// Used by tests (including those of tools embedding the library) and, with the
+/// `mock` feature, by `--provider mock`.
+#[derive(Default)]
+pub struct MockProvider {
+    prompts: Mutex<Vec<String>>,
+    availability_checks: AtomicUsize,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prompts received so far, in call order.
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().unwrap().clone()
+    }
+
+    /// How many times `is_available` was called.
+    pub fn availability_checks(&self) -> usize {
+        self.availability_checks.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, prompt: &str) {
+        self.prompts.lock().unwrap().push(prompt.to_string());
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    async fn generate(&self, prompt: &str, _options: &PromptOptions) -> Result<LlmResponse> {
+        self.record(prompt);
+
+        let words: Vec<&str> = prompt.split_whitespace().take(4).collect();
+        let title = if words.is_empty() {
+            "mock-entry.md".to_string()
+        } else {
+            format!("{}.md", words.join("-").to_lowercase())
+        };
+
+        Ok(LlmResponse {
+            title,
+            content: prompt.trim().to_string(),
+            tags: vec!["mock".to_string()],
+            tasks: vec![],
+            model: None,
+            usage: None,
+        })
+    }
+
+    async fn summarize(&self, prompt: &str) -> Result<String> {
+        self.record(prompt);
+        Ok(format!("Mock summary ({} chars)", prompt.len()))
+    }
+
+    async fn suggest_tags(&self, content: &str, _max_tags: usize) -> Result<Vec<String>> {
+        self.record(content);
+        Ok(vec!["mock".to_string()])
+    }
+
+    async fn is_available(&self) -> bool {
+        self.availability_checks.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_is_deterministic_and_records_prompts() {
+        let provider = MockProvider::new();
+        let first = provider
+            .generate("Met Jan about Q2 plans", &PromptOptions::default())
+            .await
+            .unwrap();
+        let second = provider
+            .generate("Met Jan about Q2 plans", &PromptOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(first.title, "met-jan-about-q2.md");
+        assert_eq!(first.title, second.title);
+        assert_eq!(first.content, "Met Jan about Q2 plans");
+        assert!(provider.is_available().await);
+        assert_eq!(provider.prompts().len(), 2);
+    }
+}
@@ -1,11 +1,41 @@
-use crate::config::OllamaConfig;
-use crate::providers::{sanitize_title, LlmProvider, LlmResponse, TaskItem};
+use crate::config::{OllamaConfig, RetryConfig};
+use crate::embeddings::Embedder;
+use crate::providers::{
+    build_tags_prompt, cached_availability, capture, check_status, checked_base_url,
+    cleanup_instructions, explain_timeout, extract_json, first_json_object, generate_parsed,
+    is_example_title, json_shape, log_request, normalize_tags, protect_code_blocks,
+    restore_code_blocks, shared_client, strip_thinking, tags_from_response, title_from_content,
+    unset_extra_options, with_fallback_models, with_retry, ApiError, LlmProvider, LlmResponse,
+    PromptOptions, RateLimiter, TaskItem, CHECK_TIMEOUT, SUMMARIZE_SYSTEM_PROMPT,
+    TAGS_SYSTEM_PROMPT,
+};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Built-in grammar-fixing instructions, replaced by a custom system prompt.
+const DEFAULT_INSTRUCTIONS: &str = "Fix grammar and structure this journal entry. Return JSON.";
+
+const DEFAULT_RULES: &str = r#"ABSOLUTE RULES - VIOLATION IS NOT ALLOWED:
+1. NEVER translate - keep EXACT same language as input
+2. NEVER add explanations, summaries, or meta-text like "here is", "note that", "summary of"
+3. NEVER add content not present in the original input
+4. NEVER describe what you did or add commentary
+5. Output ONLY the cleaned content, nothing else
+
+ALLOWED changes:
+- Fix spelling errors
+- Fix grammar mistakes  
+- Add punctuation
+- Split into paragraphs or bullet points for readability
+
+"#;
 
 pub struct OllamaProvider {
     config: OllamaConfig,
+    retry: RetryConfig,
+    limiter: RateLimiter,
     client: reqwest::Client,
 }
 
@@ -13,15 +43,31 @@ pub struct OllamaProvider {
 struct OllamaRequest {
     model: String,
     prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     system: Option<String>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
     format: Option<String>,
+    /// Left out entirely when nothing is set, so the server's defaults apply
+    #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize)]
 struct OllamaOptions {
-    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    /// `extra_options` the fields above leave unset
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,81 +75,151 @@ struct OllamaResponse {
     response: String,
 }
 
-/// Extract JSON object from a string that may contain markdown code blocks or extra text
-fn extract_json(raw: &str) -> String {
-    // Try to find JSON between ```json ... ``` or ``` ... ```
-    if let Some(start) = raw.find("```json") {
-        if let Some(end) = raw[start + 7..].find("```") {
-            return raw[start + 7..start + 7 + end].trim().to_string();
+/// One line of a streamed (`stream: true`) /api/generate response.
+#[derive(Debug, Deserialize)]
+struct OllamaStreamChunk {
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// One line of the streamed /api/pull progress.
+#[derive(Debug, Deserialize)]
+struct PullProgress {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    total: Option<u64>,
+    #[serde(default)]
+    completed: Option<u64>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<TagsModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsModel {
+    name: String,
+}
+
+/// Model names from an /api/tags response body.
+fn parse_model_names(body: &str) -> Result<Vec<String>> {
+    let tags: TagsResponse =
+        serde_json::from_str(body).context("Failed to parse Ollama /api/tags response")?;
+    Ok(tags.models.into_iter().map(|m| m.name).collect())
+}
+
+/// Names of the models installed on the Ollama server at `config.base_url`.
+pub async fn installed_models(config: &OllamaConfig) -> Result<Vec<String>> {
+    OllamaProvider::new(config.clone(), RetryConfig::default())?
+        .list_models()
+        .await
+}
+
+/// Whether `model` is among the `installed` names; an untagged name matches `:latest`.
+pub fn has_model(installed: &[String], model: &str) -> bool {
+    let latest = format!("{}:latest", model);
+    installed
+        .iter()
+        .any(|name| name == model || (!model.contains(':') && *name == latest))
+}
+
+/// Whether a failed request means the model isn't installed (Ollama answers 404
+/// with `model "x" not found, try pulling it first`).
+fn is_model_missing(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause.downcast_ref::<ApiError>().is_some_and(|api| {
+            api.status == reqwest::StatusCode::NOT_FOUND && api.body.contains("not found")
+        })
+    })
+}
+
+/// Reassembles NDJSON lines from arbitrarily split body chunks.
+#[derive(Default)]
+struct NdjsonAccumulator {
+    pending: Vec<u8>,
+    text: String,
+}
+
+impl NdjsonAccumulator {
+    /// Feed raw bytes; returns the `response` fragments of every completed line.
+    fn push(&mut self, bytes: &[u8]) -> Result<Vec<String>> {
+        self.pending.extend_from_slice(bytes);
+        let mut fragments = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            if let Some(fragment) = self.parse_line(&line)? {
+                fragments.push(fragment);
+            }
         }
+        Ok(fragments)
     }
-    if let Some(start) = raw.find("```") {
-        if let Some(end) = raw[start + 3..].find("```") {
-            return raw[start + 3..start + 3 + end].trim().to_string();
-        }
+
+    /// Flush a trailing line without a newline and return the full accumulated text.
+    fn finish(mut self) -> Result<String> {
+        let rest = std::mem::take(&mut self.pending);
+        self.parse_line(&rest)?;
+        Ok(self.text)
     }
-    // Try to find the first { ... } block
-    if let Some(start) = raw.find('{') {
-        if let Some(end) = raw.rfind('}') {
-            if end > start {
-                return raw[start..=end].to_string();
-            }
+
+    fn parse_line(&mut self, line: &[u8]) -> Result<Option<String>> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        let chunk: OllamaStreamChunk = serde_json::from_str(line)
+            .with_context(|| format!("Failed to parse Ollama stream chunk: {}", line))?;
+        if let Some(err) = chunk.error {
+            return Err(anyhow!("Ollama stream error: {}", err));
         }
+        self.text.push_str(&chunk.response);
+        Ok(Some(chunk.response))
     }
-    // Fallback: return as-is
-    raw.trim().to_string()
 }
 
 impl OllamaProvider {
-    pub fn new(config: OllamaConfig) -> Self {
-        Self {
+    pub fn new(mut config: OllamaConfig, retry: RetryConfig) -> Result<Self> {
+        config.base_url = checked_base_url("ollama", &config.base_url)?;
+        let client = shared_client(
+            Duration::from_secs(config.timeout_secs),
+            config.proxy.as_deref(),
+        )?;
+        Ok(Self {
             config,
-            client: reqwest::Client::new(),
-        }
+            limiter: RateLimiter::per_minute(retry.rate_limit_per_min),
+            retry,
+            client,
+        })
     }
 
-    fn build_prompt(user_input: &str) -> String {
-        format!(
-            r#"Fix grammar and structure this journal entry. Return JSON.
-
-Input: {}
-
-ABSOLUTE RULES - VIOLATION IS NOT ALLOWED:
-1. NEVER translate - keep EXACT same language as input
-2. NEVER add explanations, summaries, or meta-text like "here is", "note that", "summary of"
-3. NEVER add content not present in the original input
-4. NEVER describe what you did or add commentary
-5. Output ONLY the cleaned content, nothing else
-
-ALLOWED changes:
-- Fix spelling errors
-- Fix grammar mistakes  
-- Add punctuation
-- Split into paragraphs or bullet points for readability
-
-Title: 3-5 words, lowercase, hyphen-separated, ends with .md
-Content: cleaned content ONLY, no added commentary
-Tags: 0-3 keywords from content
-
-Return ONLY this JSON:
-{{
-  "title": "name.md",
-  "content": "cleaned content",
-  "tags": ["tag1"],
-  "tasks": [
-    {{"text": "Call Jan about Q2 planning", "priority": "normal", "due": null}}
-  ]
-}}
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout_secs)
+    }
 
-Tasks rules:
-- Extract explicit action items and to-dos from the input
-- Only include tasks that are clearly actionable
-- Keep task text short (1 sentence)
-- priority must be one of: low, normal, high
-- due must be null or ISO date string (YYYY-MM-DD)
-- If no tasks, return an empty array for tasks
-"#,
-            user_input
+    /// Build the generation prompt. A custom system prompt (per request, else
+    /// `system_prompt` from the config) replaces the built-in grammar-fixing
+    /// instructions; the JSON format section is always kept.
+    fn build_prompt(&self, user_input: &str, options: &PromptOptions) -> String {
+        let system = options.system_or(self.config.system_prompt.as_deref());
+        let (instructions, rules) = match system {
+            Some(custom) => (custom, ""),
+            None => (DEFAULT_INSTRUCTIONS, DEFAULT_RULES),
+        };
+        format!(
+            "{instructions}\n\nInput: {input}\n\n{rules}{fields}\n\n{shape}\n{extra}",
+            instructions = instructions,
+            input = user_input,
+            rules = rules,
+            fields = cleanup_instructions(options),
+            shape = json_shape(),
+            extra = options.extra_instructions()
         )
     }
 
@@ -134,71 +250,310 @@ Rules:
         )
     }
 
-    async fn call_ollama_json(&self, prompt: &str, system_prompt: Option<&str>) -> Result<String> {
+    async fn call_ollama_json(
+        &self,
+        model: &str,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        stream: bool,
+    ) -> Result<String> {
         let request = OllamaRequest {
-            model: self.config.model.clone(),
-            prompt: prompt.to_string(),
-            system: system_prompt.map(|s| s.to_string()),
-            stream: false,
-            format: Some("json".to_string()),
-            options: Some(OllamaOptions { temperature: 0.1 }),
+            model: model.to_string(),
+            ..self.build_json_request(prompt, system_prompt, stream)
         };
+        let raw = self.send_generate(&request).await?;
+        // `format: "json"` isn't enforced by every model; take the object out of any prose
+        // around it, and leave the rest to the strict parse when there is none
+        let extracted = first_json_object(&strip_thinking(&raw)).map(str::to_string);
+        Ok(extracted.unwrap_or(raw))
+    }
 
-        let url = format!("{}/api/generate", self.config.base_url);
+    /// Send a generate request, pulling the model and retrying once if it is
+    /// missing and `auto_pull` is on.
+    async fn send_generate(&self, request: &OllamaRequest) -> Result<String> {
+        let result = if request.stream {
+            self.post_generate_streaming(request).await
+        } else {
+            self.post_generate(request).await
+        };
 
+        match result {
+            Err(err) if self.config.auto_pull && is_model_missing(&err) => {
+                self.pull_model(&request.model).await?;
+                if request.stream {
+                    self.post_generate_streaming(request).await
+                } else {
+                    self.post_generate(request).await
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// `keep_alive` as Ollama reads it: a plain number of seconds (e.g. -1 for forever)
+    /// must be sent as a JSON number, since a string needs a unit ("10m", "-1m").
+    fn keep_alive(&self) -> Option<serde_json::Value> {
+        let keep_alive = self.config.keep_alive.as_deref()?.trim();
+        Some(match keep_alive.parse::<i64>() {
+            Ok(seconds) => seconds.into(),
+            Err(_) => keep_alive.into(),
+        })
+    }
+
+    /// Download `model` via /api/pull, reporting progress on stderr unless warnings are
+    /// off (`--quiet`).
+    async fn pull_model(&self, model: &str) -> Result<()> {
+        use std::io::Write;
+
+        let url = format!("{}/api/pull", self.config.base_url);
+        let show_progress = tracing::enabled!(tracing::Level::WARN);
+        if show_progress {
+            eprintln!("Model {} not found, pulling it...", model);
+        }
+
+        // Older Ollama versions read `name`, newer ones `model`
+        let body = serde_json::json!({
+            "model": model,
+            "name": model,
+            "stream": true,
+        });
         let response = self
             .client
             .post(&url)
-            .json(&request)
+            .timeout(Duration::from_secs(60 * 60))
+            .json(&body)
             .send()
             .await
             .with_context(|| format!("Failed to connect to Ollama at {}", self.config.base_url))?;
+        let mut response = check_status("Ollama", response).await?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Ollama API error {}: {}", status, text));
+        let mut pending: Vec<u8> = Vec::new();
+        let mut stderr = std::io::stderr();
+        while let Some(bytes) = response
+            .chunk()
+            .await
+            .context("Failed to read pull progress")?
+        {
+            pending.extend_from_slice(&bytes);
+            while let Some(pos) = pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let progress: PullProgress = serde_json::from_str(line.trim())
+                    .with_context(|| format!("Failed to parse pull progress: {}", line.trim()))?;
+                if let Some(err) = progress.error {
+                    if show_progress {
+                        let _ = writeln!(stderr);
+                    }
+                    return Err(anyhow!("Failed to pull {}: {}", model, err));
+                }
+                if !show_progress {
+                    continue;
+                }
+                match (progress.completed, progress.total) {
+                    (Some(done), Some(total)) if total > 0 => {
+                        let _ = write!(stderr, "\r{} {:>3}%", progress.status, done * 100 / total);
+                    }
+                    _ => {
+                        let _ = write!(stderr, "\r{}", progress.status);
+                    }
+                }
+                let _ = stderr.flush();
+            }
+        }
+        if show_progress {
+            let _ = writeln!(stderr);
+        }
+        Ok(())
+    }
+
+    /// Names of the models installed on the Ollama server.
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let url = format!("{}/api/tags", self.config.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to Ollama at {}", self.config.base_url))?;
+        let body = check_status("Ollama", response).await?.text().await?;
+        parse_model_names(&body)
+    }
+
+    fn build_json_request(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        stream: bool,
+    ) -> OllamaRequest {
+        OllamaRequest {
+            model: self.config.model.clone(),
+            prompt: prompt.to_string(),
+            system: system_prompt.map(|s| s.to_string()),
+            stream,
+            format: Some("json".to_string()),
+            options: self.options(self.config.temperature),
+            keep_alive: self.keep_alive(),
+        }
+    }
+
+    /// Request options with `temperature`, the configured limits, stop sequences and
+    /// `extra_options`; `None` when none of them is set.
+    fn options(&self, temperature: Option<f32>) -> Option<OllamaOptions> {
+        let mut options = OllamaOptions {
+            temperature,
+            num_predict: self.config.max_tokens,
+            num_ctx: self.config.num_ctx,
+            stop: self.config.stop.clone(),
+            extra: serde_json::Map::new(),
+        };
+        options.extra = unset_extra_options(&options, &self.config.extra_options);
+        if options.temperature.is_none()
+            && options.num_predict.is_none()
+            && options.num_ctx.is_none()
+            && options.stop.is_empty()
+            && options.extra.is_empty()
+        {
+            return None;
         }
+        Some(options)
+    }
+
+    /// POST to /api/generate, retrying transient failures, and return the `response` field.
+    async fn post_generate(&self, request: &OllamaRequest) -> Result<String> {
+        let url = format!("{}/api/generate", self.config.base_url);
+        log_request("Ollama", &url, &request.model, request, None);
+
+        let response = with_retry(&self.retry, || async {
+            self.limiter.wait().await;
+            let response = capture::send("Ollama", self.client.post(&url).json(request))
+                .await
+                .with_context(|| {
+                    format!("Failed to connect to Ollama at {}", self.config.base_url)
+                })?;
+            check_status("Ollama", response).await
+        })
+        .await
+        .map_err(|e| explain_timeout(e, self.timeout()))?;
 
         let ollama_resp: OllamaResponse = response
             .json()
             .await
-            .context("Failed to parse Ollama response")?;
+            .context("Failed to parse Ollama response")
+            .map_err(|e| explain_timeout(e, self.timeout()))?;
 
         Ok(ollama_resp.response)
     }
 
-    async fn generate_tasks(&self, clean_content: &str, system_prompt: Option<&str>) -> Result<Vec<TaskItem>> {
+    /// POST a streaming request to /api/generate, echoing tokens to stderr as they
+    /// arrive, and return the accumulated `response` text.
+    async fn post_generate_streaming(&self, request: &OllamaRequest) -> Result<String> {
+        use std::io::Write;
+
+        let url = format!("{}/api/generate", self.config.base_url);
+        log_request("Ollama", &url, &request.model, request, None);
+
+        let mut response = with_retry(&self.retry, || async {
+            self.limiter.wait().await;
+            let response = capture::send("Ollama", self.client.post(&url).json(request))
+                .await
+                .with_context(|| {
+                    format!("Failed to connect to Ollama at {}", self.config.base_url)
+                })?;
+            check_status("Ollama", response).await
+        })
+        .await
+        .map_err(|e| explain_timeout(e, self.timeout()))?;
+
+        let mut acc = NdjsonAccumulator::default();
+        let mut stderr = std::io::stderr();
+        while let Some(bytes) = response
+            .chunk()
+            .await
+            .context("Failed to read Ollama stream")
+            .map_err(|e| explain_timeout(e, self.timeout()))?
+        {
+            for fragment in acc.push(&bytes)? {
+                let _ = write!(stderr, "{}", fragment);
+                let _ = stderr.flush();
+            }
+        }
+        let _ = writeln!(stderr);
+
+        acc.finish()
+    }
+
+    fn build_summarize_request(&self, prompt: &str) -> OllamaRequest {
+        self.build_text_request(SUMMARIZE_SYSTEM_PROMPT, prompt)
+    }
+
+    fn build_tags_request(&self, content: &str, max_tags: usize) -> OllamaRequest {
+        self.build_text_request(TAGS_SYSTEM_PROMPT, &build_tags_prompt(content, max_tags))
+    }
+
+    /// Plain-text (not JSON mode) request for `prompt` under `system`.
+    fn build_text_request(&self, system: &str, prompt: &str) -> OllamaRequest {
+        OllamaRequest {
+            model: self.config.model.clone(),
+            prompt: prompt.to_string(),
+            system: Some(system.to_string()),
+            stream: false,
+            format: None,
+            options: self.options(self.config.temperature),
+            keep_alive: self.keep_alive(),
+        }
+    }
+
+    async fn generate_tasks(
+        &self,
+        model: &str,
+        clean_content: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<Vec<TaskItem>> {
         #[derive(Deserialize)]
         struct TasksOnly {
             tasks: Option<Vec<TaskItem>>,
         }
 
         let tasks_prompt = Self::build_tasks_prompt(clean_content);
-        let raw = self.call_ollama_json(&tasks_prompt, system_prompt).await?;
+        let raw = self
+            .call_ollama_json(model, &tasks_prompt, system_prompt, false)
+            .await?;
+        let raw = strip_thinking(&raw);
         let json_str = extract_json(&raw);
 
-        let parsed: TasksOnly = serde_json::from_str(&json_str)
+        let parsed: TasksOnly = serde_json::from_str(json_str)
             .with_context(|| format!("Failed to parse tasks JSON response: {}", raw))?;
 
         Ok(parsed.tasks.unwrap_or_default())
     }
-}
 
-#[async_trait]
-impl LlmProvider for OllamaProvider {
-    async fn generate(&self, prompt: &str, system_prompt: Option<&str>) -> Result<LlmResponse> {
-        let full_prompt = Self::build_prompt(prompt);
-
-        let raw = self.call_ollama_json(&full_prompt, system_prompt).await?;
-        let json_str = extract_json(&raw);
-
-        let llm_response: LlmResponse = serde_json::from_str(&json_str)
-            .with_context(|| format!("Failed to parse LLM JSON response: {}", raw))?;
+    /// Generate an entry with `model`, one of the configured model and its fallbacks.
+    async fn generate_with_model(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: &PromptOptions,
+    ) -> Result<LlmResponse> {
+        // Code blocks are swapped for placeholders so the cleanup can't reflow them
+        let (protected, code_blocks) = protect_code_blocks(prompt);
+        let protected = &protected;
+        let llm_response =
+            generate_parsed(self.retry.json_retries, options, |options| async move {
+                // The system prompt is folded into the prompt text in place of the built-in rules
+                let full_prompt = self.build_prompt(protected, &options);
+                self.call_ollama_json(model, &full_prompt, None, self.config.stream)
+                    .await
+            })
+            .await?;
 
         fn strip_prompt_echo(s: &str) -> String {
             let markers = [
                 "ABSOLUTE RULES",
+                "Return JSON with these exact fields",
                 "Return ONLY this JSON",
                 "Tasks rules:",
                 "Title:",
@@ -216,19 +571,19 @@ impl LlmProvider for OllamaProvider {
             s[..cut].trim().to_string()
         }
 
-        let cleaned_content = strip_prompt_echo(&llm_response.content);
+        let cleaned_content =
+            restore_code_blocks(&strip_prompt_echo(&llm_response.content), &code_blocks);
 
         // If we still see prompt instructions after stripping, fail loudly.
-        if cleaned_content.contains("ABSOLUTE RULES") || cleaned_content.contains("Return ONLY this JSON") {
+        if cleaned_content.contains("ABSOLUTE RULES")
+            || cleaned_content.contains("Return ONLY this JSON")
+        {
             return Err(anyhow!(
-                "LLM returned prompt instructions as content — model may not support JSON mode. Raw response: {}",
-                raw
+                "LLM returned prompt instructions as content — model may not support JSON mode. Content: {}",
+                llm_response.content
             ));
         }
 
-        // Sanitize the title
-        let title = sanitize_title(&llm_response.title);
-
         fn has_time_signal(s: &str) -> bool {
             let s_l = s.to_lowercase();
             // numeric dates (language-agnostic)
@@ -275,14 +630,21 @@ impl LlmProvider for OllamaProvider {
             needles.iter().any(|n| s_l.contains(n))
         }
 
-        let mut tasks = match self.generate_tasks(&cleaned_content, system_prompt).await {
-            Ok(t) => t,
-            Err(_) => vec![],
-        };
+        let mut tasks = self
+            .generate_tasks(
+                model,
+                &cleaned_content,
+                options.system_or(self.config.system_prompt.as_deref()),
+            )
+            .await
+            .unwrap_or_default();
 
         // Conservative fallback: if the model returns 0 tasks but the note looks like scheduled work,
         // create a single task from the first non-empty line.
-        if tasks.is_empty() && has_time_signal(&cleaned_content) && has_action_signal(&cleaned_content) {
+        if tasks.is_empty()
+            && has_time_signal(&cleaned_content)
+            && has_action_signal(&cleaned_content)
+        {
             let first_line = cleaned_content
                 .lines()
                 .map(|l| l.trim())
@@ -298,71 +660,112 @@ impl LlmProvider for OllamaProvider {
             }
         }
 
+        // Some models leave the title empty; name the entry after its first sentence
+        let title = if llm_response.title.trim().is_empty() {
+            title_from_content(&cleaned_content)
+        } else if is_example_title(&llm_response.title) {
+            // The example placeholder came back; use the first few words of the note
+            let words: Vec<&str> = prompt.split_whitespace().take(5).collect();
+            words.join(" ")
+        } else {
+            llm_response.title
+        };
+
         Ok(LlmResponse {
             title,
             content: cleaned_content,
-            tags: llm_response.tags,
+            tags: options.limit_tags(normalize_tags(llm_response.tags)),
             tasks,
+            model: None,
+            usage: None,
         })
     }
+}
 
-    async fn summarize(&self, prompt: &str) -> Result<String> {
-        let request = OllamaRequest {
-            model: self.config.model.clone(),
-            prompt: prompt.to_string(),
-            system: Some("You are a helpful assistant that summarizes journal entries. Be concise and highlight key points. IMPORTANT: Always respond in the SAME language as the journal entries - never translate to another language.".to_string()),
-            stream: false,
-            format: None,
-            options: Some(OllamaOptions { temperature: 0.3 }),
-        };
-
-        let url = format!("{}/api/generate", self.config.base_url);
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn prompt_text(&self, prompt: &str, options: &PromptOptions) -> Option<String> {
+        let (protected, _) = protect_code_blocks(prompt);
+        Some(self.build_prompt(&protected, options))
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .with_context(|| format!("Failed to connect to Ollama at {}", self.config.base_url))?;
+    async fn generate(&self, prompt: &str, options: &PromptOptions) -> Result<LlmResponse> {
+        let fallbacks = &self.config.fallback_models;
+        let (response, model) =
+            with_fallback_models(&self.config.model, fallbacks, |model| async move {
+                self.generate_with_model(&model, prompt, options).await
+            })
+            .await?;
+        Ok(LlmResponse {
+            model: Some(model),
+            ..response
+        })
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Ollama API error {}: {}", status, text));
-        }
+    async fn summarize(&self, prompt: &str) -> Result<String> {
+        let request = self.build_summarize_request(prompt);
+        self.send_generate(&request).await
+    }
 
-        let ollama_resp: OllamaResponse = response
-            .json()
-            .await
-            .context("Failed to parse Ollama response")?;
+    async fn suggest_tags(&self, content: &str, max_tags: usize) -> Result<Vec<String>> {
+        let request = self.build_tags_request(content, max_tags);
+        tags_from_response(&self.send_generate(&request).await?, max_tags)
+    }
 
-        Ok(ollama_resp.response)
+    async fn is_available(&self) -> bool {
+        cached_availability("ollama", &self.config.base_url, || async {
+            // Any HTTP answer means the server is up; Ollama replies "Ollama is running" on /
+            self.client
+                .get(&self.config.base_url)
+                .timeout(CHECK_TIMEOUT)
+                .send()
+                .await
+                .is_ok()
+        })
+        .await
     }
+}
 
-    fn is_available(&self) -> bool {
-        // Non-blocking availability check suitable for calling from within a Tokio runtime.
-        // We simply attempt a short TCP connect to the host:port from base_url.
-        use std::net::{TcpStream, ToSocketAddrs};
-        use std::time::Duration;
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    embedding: Vec<f32>,
+}
 
-        let base = self.config.base_url.trim();
-        let base = base
-            .strip_prefix("http://")
-            .or_else(|| base.strip_prefix("https://"))
-            .unwrap_or(base);
-        let host_port = base.split('/').next().unwrap_or(base);
+#[async_trait]
+impl Embedder for OllamaProvider {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let url = format!("{}/api/embeddings", self.config.base_url);
+        let body = serde_json::json!({
+            "model": self.config.embedding_model,
+            "prompt": text,
+        });
 
-        let mut addrs_iter = match host_port.to_socket_addrs() {
-            Ok(it) => it,
-            Err(_) => return false,
-        };
+        let response = with_retry(&self.retry, || async {
+            self.limiter.wait().await;
+            let response = capture::send("Ollama", self.client.post(&url).json(&body))
+                .await
+                .with_context(|| {
+                    format!("Failed to connect to Ollama at {}", self.config.base_url)
+                })?;
+            check_status("Ollama", response).await
+        })
+        .await?;
 
-        if let Some(addr) = addrs_iter.next() {
-            TcpStream::connect_timeout(&addr, Duration::from_secs(2)).is_ok()
-        } else {
-            false
+        let embedding: EmbeddingResponse = response
+            .json()
+            .await
+            .context("Failed to parse Ollama embeddings response")?;
+        if embedding.embedding.is_empty() {
+            return Err(anyhow!(
+                "Ollama returned no embedding; is {} an embedding model?",
+                self.config.embedding_model
+            ));
         }
+        Ok(embedding.embedding)
+    }
+
+    fn embedding_model(&self) -> &str {
+        &self.config.embedding_model
     }
 }
 
@@ -370,13 +773,812 @@ impl LlmProvider for OllamaProvider {
 mod tests {
     use super::*;
 
+    fn provider() -> OllamaProvider {
+        OllamaProvider::new(OllamaConfig::default(), RetryConfig::default()).unwrap()
+    }
+
     #[test]
     fn test_build_prompt() {
-        let prompt = OllamaProvider::build_prompt("Meeting with team");
+        let prompt = provider().build_prompt("Meeting with team", &PromptOptions::default());
         assert!(prompt.contains("Fix grammar"));
         assert!(prompt.contains("Meeting with team"));
         assert!(prompt.contains("JSON"));
         assert!(prompt.contains("NEVER"));
         assert!(prompt.contains("NO added commentary") || prompt.contains("commentary"));
     }
+
+    #[test]
+    fn test_build_prompt_mentions_tag_count() {
+        let options = PromptOptions {
+            max_tags: 4,
+            ..PromptOptions::default()
+        };
+        let prompt = provider().build_prompt("Meeting with team", &options);
+        assert!(prompt.contains("\"tags\": 0-4 relevant keywords"));
+        assert!(!prompt.contains("0-3"));
+    }
+
+    #[test]
+    fn test_build_prompt_with_cleanup_level() {
+        let options = PromptOptions {
+            cleanup: crate::providers::Cleanup::Heavy,
+            ..PromptOptions::default()
+        };
+        let prompt = provider().build_prompt("Meeting with team", &options);
+        assert!(prompt.contains("markdown headings (##)"));
+        assert!(prompt.contains("Meeting with team"));
+    }
+
+    #[test]
+    fn test_build_prompt_with_tone_keeps_language_rule() {
+        let options = PromptOptions {
+            tone: crate::providers::Tone::Formal,
+            ..PromptOptions::default()
+        };
+        let prompt = provider().build_prompt("Meeting with team", &options);
+        assert!(prompt.contains("in a formal tone"));
+        assert!(prompt.contains("NEVER translate"));
+        // Appended after the built-in rules rather than replacing them
+        assert!(prompt.find("NEVER translate") < prompt.find("in a formal tone"));
+    }
+
+    #[test]
+    fn test_build_prompt_custom_system_prompt() {
+        let options = PromptOptions {
+            system: Some("Write like a pirate.".to_string()),
+            ..PromptOptions::default()
+        };
+        let prompt = provider().build_prompt("Meeting with team", &options);
+        assert!(prompt.starts_with("Write like a pirate."));
+        assert!(!prompt.contains("Fix grammar"));
+        assert!(!prompt.contains("ABSOLUTE RULES"));
+        assert!(prompt.contains("Meeting with team"));
+        assert!(prompt.contains("Return ONLY this JSON"));
+    }
+
+    #[test]
+    fn test_system_prompt_precedence() {
+        let configured = OllamaProvider::new(
+            OllamaConfig {
+                system_prompt: Some("Keep it short for a small model.".to_string()),
+                ..OllamaConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let per_request = PromptOptions {
+            system: Some("Write like a pirate.".to_string()),
+            ..PromptOptions::default()
+        };
+
+        // The request's prompt beats the config, which beats the built-in
+        let prompt = configured.build_prompt("Note", &per_request);
+        assert!(prompt.starts_with("Write like a pirate."));
+        let prompt = configured.build_prompt("Note", &PromptOptions::default());
+        assert!(prompt.starts_with("Keep it short for a small model."));
+        assert!(!prompt.contains("Fix grammar"));
+        let prompt = provider().build_prompt("Note", &PromptOptions::default());
+        assert!(prompt.contains("Fix grammar"));
+    }
+
+    #[test]
+    fn test_build_prompt_with_template() {
+        let options = PromptOptions {
+            template: Some("Start with a `Mood:` line.".to_string()),
+            ..PromptOptions::default()
+        };
+        let prompt = provider().build_prompt("Long day", &options);
+        assert!(prompt.contains("Fix grammar"));
+        assert!(prompt.trim_end().ends_with("Start with a `Mood:` line."));
+    }
+
+    #[test]
+    fn test_json_request_uses_configured_temperature() {
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                temperature: Some(0.9),
+                ..OllamaConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let request = provider.build_json_request("note", None, false);
+        let body = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(body["options"]["temperature"].as_f64().unwrap() as f32, 0.9);
+        assert_eq!(body["format"], "json");
+    }
+
+    #[test]
+    fn test_unset_temperature_omits_options() {
+        let body =
+            serde_json::to_value(provider().build_json_request("note", None, false)).unwrap();
+        assert!(body.get("options").is_none());
+        assert!(body.get("system").is_none());
+        assert_eq!(body["format"], "json");
+
+        let body =
+            serde_json::to_value(provider().build_json_request("note", Some("Be terse."), false))
+                .unwrap();
+        assert_eq!(body["system"], "Be terse.");
+    }
+
+    #[test]
+    fn test_num_predict_only_when_configured() {
+        let provider = provider();
+        let body = serde_json::to_value(provider.build_json_request("note", None, false)).unwrap();
+        assert!(body["options"].get("num_predict").is_none());
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                max_tokens: Some(256),
+                ..OllamaConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let body = serde_json::to_value(provider.build_json_request("note", None, false)).unwrap();
+        assert_eq!(body["options"]["num_predict"], 256);
+        let body = serde_json::to_value(provider.build_summarize_request("entries")).unwrap();
+        assert_eq!(body["options"]["num_predict"], 256);
+    }
+
+    #[test]
+    fn test_stop_sequences_in_options() {
+        let config: OllamaConfig = toml::from_str(r#"stop = ["<|eot_id|>", "\n\n\n"]"#).unwrap();
+        let provider = OllamaProvider::new(config, RetryConfig::default()).unwrap();
+        let body = serde_json::to_value(provider.build_json_request("note", None, false)).unwrap();
+        assert_eq!(
+            body["options"]["stop"],
+            serde_json::json!(["<|eot_id|>", "\n\n\n"])
+        );
+
+        let provider =
+            OllamaProvider::new(OllamaConfig::default(), RetryConfig::default()).unwrap();
+        let body = serde_json::to_value(provider.build_json_request("note", None, false)).unwrap();
+        assert!(body
+            .get("options")
+            .is_none_or(|options| options.get("stop").is_none()));
+    }
+
+    #[test]
+    fn test_extra_options_merge_into_options() {
+        let config: OllamaConfig = toml::from_str(
+            r#"
+            temperature = 0.2
+            extra_options = { top_k = 40, repeat_penalty = 1.1, temperature = 0.9 }
+            "#,
+        )
+        .unwrap();
+        let provider = OllamaProvider::new(config, RetryConfig::default()).unwrap();
+        let body = serde_json::to_value(provider.build_json_request("note", None, false)).unwrap();
+
+        assert_eq!(body["options"]["top_k"], 40);
+        assert_eq!(body["options"]["repeat_penalty"].as_f64().unwrap(), 1.1);
+        // The first-class setting wins over the same key in extra_options
+        assert_eq!(body["options"]["temperature"].as_f64().unwrap() as f32, 0.2);
+
+        let config: OllamaConfig = toml::from_str("extra_options = { top_k = 40 }").unwrap();
+        let provider = OllamaProvider::new(config, RetryConfig::default()).unwrap();
+        let body = serde_json::to_value(provider.build_summarize_request("entries")).unwrap();
+        assert_eq!(body["options"], serde_json::json!({ "top_k": 40 }));
+    }
+
+    #[test]
+    fn test_summarize_request_uses_configured_temperature() {
+        let body = serde_json::to_value(provider().build_summarize_request("entries")).unwrap();
+        assert!(body.get("options").is_none());
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                temperature: Some(0.9),
+                max_tokens: Some(128),
+                ..OllamaConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let body = serde_json::to_value(provider.build_summarize_request("entries")).unwrap();
+        assert_eq!(body["options"]["temperature"].as_f64().unwrap() as f32, 0.9);
+        assert_eq!(body["options"]["num_predict"], 128);
+    }
+
+    #[test]
+    fn test_keep_alive_and_num_ctx_only_when_configured() {
+        let provider = provider();
+        let body = serde_json::to_value(provider.build_json_request("note", None, false)).unwrap();
+        assert!(body.get("keep_alive").is_none());
+        assert!(body["options"].get("num_ctx").is_none());
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                keep_alive: Some("30m".to_string()),
+                num_ctx: Some(8192),
+                ..OllamaConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        for request in [
+            provider.build_json_request("note", None, false),
+            provider.build_summarize_request("entries"),
+        ] {
+            let body = serde_json::to_value(request).unwrap();
+            assert_eq!(body["keep_alive"], "30m");
+            assert_eq!(body["options"]["num_ctx"], 8192);
+        }
+    }
+
+    #[test]
+    fn test_numeric_keep_alive_sent_as_number() {
+        for (keep_alive, expected) in [
+            ("-1", serde_json::json!(-1)),
+            ("0", serde_json::json!(0)),
+            ("3600", serde_json::json!(3600)),
+            ("-1m", serde_json::json!("-1m")),
+        ] {
+            let provider = OllamaProvider::new(
+                OllamaConfig {
+                    keep_alive: Some(keep_alive.to_string()),
+                    ..OllamaConfig::default()
+                },
+                RetryConfig::default(),
+            )
+            .unwrap();
+            let body =
+                serde_json::to_value(provider.build_json_request("note", None, false)).unwrap();
+            assert_eq!(body["keep_alive"], expected, "{}", keep_alive);
+        }
+    }
+
+    #[test]
+    fn test_summarize_request_body() {
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                model: "mistral".to_string(),
+                ..OllamaConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let request = provider.build_summarize_request("Summarize these entries");
+        let body = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(body["model"], "mistral");
+        assert_eq!(body["prompt"], "Summarize these entries");
+        assert_eq!(body["stream"], false);
+        assert!(body["system"]
+            .as_str()
+            .unwrap()
+            .contains("summarizes journal entries"));
+    }
+
+    fn fast_retry() -> RetryConfig {
+        RetryConfig {
+            max_retries: 3,
+            base_delay_ms: 1,
+            ..RetryConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embed_posts_embedding_model() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/embeddings")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "model": "nomic-embed-text",
+                "prompt": "Call Jan",
+            })))
+            .with_status(200)
+            .with_body(r#"{"embedding": [0.5, -0.25, 1.0]}"#)
+            .create_async()
+            .await;
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                ..OllamaConfig::default()
+            },
+            fast_retry(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            provider.embed("Call Jan").await.unwrap(),
+            vec![0.5, -0.25, 1.0]
+        );
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_summarize_retries_transient_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server
+            .mock("POST", "/api/generate")
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("POST", "/api/generate")
+            .with_status(200)
+            .with_body(r#"{"response": "summary"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                ..OllamaConfig::default()
+            },
+            fast_retry(),
+        )
+        .unwrap();
+
+        let summary = provider.summarize("entries").await.unwrap();
+        assert_eq!(summary, "summary");
+        unavailable.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_summarize_does_not_retry_client_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let not_found = server
+            .mock("POST", "/api/generate")
+            .with_status(404)
+            .with_body("model not found")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                ..OllamaConfig::default()
+            },
+            fast_retry(),
+        )
+        .unwrap();
+
+        let err = provider.summarize("entries").await.unwrap_err();
+        assert!(err.to_string().contains("404"));
+        not_found.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_generate_asks_again_for_json_after_prose() {
+        let mut server = mockito::Server::new_async().await;
+        // Created first so the retry, which carries the stricter instruction, lands here
+        let strict = server
+            .mock("POST", "/api/generate")
+            .match_body(mockito::Matcher::Regex(
+                "You MUST return only valid JSON".to_string(),
+            ))
+            .with_status(200)
+            .with_body(
+                r#"{"response": "{\"title\": \"lunch.md\", \"content\": \"Lunch with Sam.\", \"tags\": [\"food\"]}"}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let prose = server
+            .mock("POST", "/api/generate")
+            .with_status(200)
+            .with_body(r#"{"response": "Sure! Here is a tidy note about lunch."}"#)
+            // The first attempt, then the separate task extraction pass
+            .expect(2)
+            .create_async()
+            .await;
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                ..OllamaConfig::default()
+            },
+            fast_retry(),
+        )
+        .unwrap();
+
+        let response = provider
+            .generate("Lunch with Sam", &PromptOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.title, "lunch.md");
+        prose.assert_async().await;
+        strict.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_generate_falls_back_to_next_model_on_garbage() {
+        let mut server = mockito::Server::new_async().await;
+        let garbage = server
+            .mock("POST", "/api/generate")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({"model": "llama3.2"}),
+            ))
+            .with_status(200)
+            .with_body(r#"{"response": "lunch lunch lunch"}"#)
+            // The first attempt and the ask for strict JSON
+            .expect(2)
+            .create_async()
+            .await;
+        let valid = server
+            .mock("POST", "/api/generate")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({"model": "qwen2.5"}),
+            ))
+            .with_status(200)
+            .with_body(
+                r#"{"response": "{\"title\": \"lunch.md\", \"content\": \"Lunch with Sam.\", \"tags\": [\"food\"], \"tasks\": []}"}"#,
+            )
+            // The entry, then the task extraction pass on the same model
+            .expect(2)
+            .create_async()
+            .await;
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                model: "llama3.2".to_string(),
+                fallback_models: vec!["qwen2.5".to_string()],
+                ..OllamaConfig::default()
+            },
+            fast_retry(),
+        )
+        .unwrap();
+
+        let response = provider
+            .generate("Lunch with Sam", &PromptOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.title, "lunch.md");
+        assert_eq!(response.content, "Lunch with Sam.");
+        assert_eq!(response.model.as_deref(), Some("qwen2.5"));
+        garbage.assert_async().await;
+        valid.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_generate_takes_json_out_of_surrounding_prose() {
+        let mut server = mockito::Server::new_async().await;
+        let leading = server
+            .mock("POST", "/api/generate")
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "response": "Here is the JSON:\n{\"title\": \"lunch-with-sam.md\", \"content\": \"Lunch with Sam.\", \"tags\": [\"food\"], \"tasks\": []}"
+                })
+                .to_string(),
+            )
+            // Tasks may get a second pass of their own
+            .expect_at_least(1)
+            .create_async()
+            .await;
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                ..OllamaConfig::default()
+            },
+            RetryConfig {
+                json_retries: 0,
+                ..fast_retry()
+            },
+        )
+        .unwrap();
+        let response = provider
+            .generate("Lunch with Sam", &PromptOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.title, "lunch-with-sam.md");
+        assert_eq!(response.tags, vec!["food"]);
+        leading.assert_async().await;
+        leading.remove_async().await;
+
+        let trailing = server
+            .mock("POST", "/api/generate")
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "response": "{\"title\": \"lunch-with-sam.md\", \"content\": \"Lunch with Sam.\", \"tags\": [], \"tasks\": []}\nLet me know if you need {anything} else!"
+                })
+                .to_string(),
+            )
+            // Tasks may get a second pass of their own
+            .expect_at_least(1)
+            .create_async()
+            .await;
+        let response = provider
+            .generate("Lunch with Sam", &PromptOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Lunch with Sam.");
+        trailing.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_generate_json_retries_zero_fails_on_prose() {
+        let mut server = mockito::Server::new_async().await;
+        let prose = server
+            .mock("POST", "/api/generate")
+            .with_status(200)
+            .with_body(r#"{"response": "Sure! Here is a tidy note about lunch."}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                ..OllamaConfig::default()
+            },
+            RetryConfig {
+                json_retries: 0,
+                ..fast_retry()
+            },
+        )
+        .unwrap();
+
+        assert!(provider
+            .generate("Lunch with Sam", &PromptOptions::default())
+            .await
+            .is_err());
+        prose.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_installed_models_from_tags() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/tags")
+            .with_body(r#"{"models": [{"name": "llama3.2:latest"}, {"name": "gemma2:2b"}]}"#)
+            .create_async()
+            .await;
+        let config = OllamaConfig {
+            base_url: server.url(),
+            ..OllamaConfig::default()
+        };
+        assert_eq!(
+            installed_models(&config).await.unwrap(),
+            vec!["llama3.2:latest", "gemma2:2b"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_is_available() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/")
+            .with_body("Ollama is running")
+            .create_async()
+            .await;
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                ..OllamaConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        assert!(provider.is_available().await);
+
+        let unreachable = OllamaProvider::new(
+            OllamaConfig {
+                base_url: "http://127.0.0.1:9".to_string(),
+                ..OllamaConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        assert!(!unreachable.is_available().await);
+    }
+
+    #[test]
+    fn test_new_checks_base_url() {
+        let with_url = |base_url: &str| {
+            OllamaProvider::new(
+                OllamaConfig {
+                    base_url: base_url.to_string(),
+                    ..OllamaConfig::default()
+                },
+                RetryConfig::default(),
+            )
+        };
+        let err = with_url("localhost:11434").err().unwrap().to_string();
+        assert!(
+            err.contains("[ollama] base_url 'localhost:11434'"),
+            "{}",
+            err
+        );
+        assert!(err.contains("expected http or https"), "{}", err);
+
+        let provider = with_url("http://gpu-box:11434//").unwrap();
+        assert_eq!(provider.config.base_url, "http://gpu-box:11434");
+        let provider = with_url("https://ollama.example.com/base").unwrap();
+        assert_eq!(provider.config.base_url, "https://ollama.example.com/base");
+    }
+
+    #[tokio::test]
+    async fn test_is_available_reuses_recent_result() {
+        let mut server = mockito::Server::new_async().await;
+        // mockito reuses servers across tests, so a path keeps this cache entry unique
+        let root = server
+            .mock("GET", "/reuse")
+            .with_body("Ollama is running")
+            .expect(1)
+            .create_async()
+            .await;
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: format!("{}/reuse", server.url()),
+                ..OllamaConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+
+        assert!(provider.is_available().await);
+        assert!(provider.is_available().await);
+        root.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_installed_models_unreachable() {
+        let config = OllamaConfig {
+            base_url: "http://127.0.0.1:9".to_string(),
+            ..OllamaConfig::default()
+        };
+        assert!(installed_models(&config).await.is_err());
+    }
+
+    #[test]
+    fn test_detects_missing_configured_model() {
+        let body = r#"{"models": [
+            {"name": "llama3.2:latest", "size": 2019393189},
+            {"name": "gemma2:2b", "size": 1629518495}
+        ]}"#;
+        let installed = parse_model_names(body).unwrap();
+        assert!(has_model(&installed, "llama3.2"));
+        assert!(has_model(&installed, "llama3.2:latest"));
+        assert!(has_model(&installed, "gemma2:2b"));
+        assert!(!has_model(&installed, "gemma2"));
+        assert!(!has_model(&installed, "mistral"));
+        assert!(!has_model(&installed, "llama3.2:1b"));
+    }
+
+    #[test]
+    fn test_parse_model_names() {
+        let body = r#"{"models": [
+            {"name": "llama3.2:latest", "size": 2019393189},
+            {"name": "gemma2:2b", "size": 1629518495}
+        ]}"#;
+        assert_eq!(
+            parse_model_names(body).unwrap(),
+            vec!["llama3.2:latest", "gemma2:2b"]
+        );
+        assert!(parse_model_names(r#"{}"#).unwrap().is_empty());
+        assert!(parse_model_names("not json").is_err());
+    }
+
+    #[test]
+    fn test_is_model_missing() {
+        let missing: anyhow::Error = ApiError {
+            provider: "Ollama",
+            status: reqwest::StatusCode::NOT_FOUND,
+            body: r#"{"error":"model \"llama3.2\" not found, try pulling it first"}"#.to_string(),
+        }
+        .into();
+        assert!(is_model_missing(&missing));
+
+        let other_404: anyhow::Error = ApiError {
+            provider: "Ollama",
+            status: reqwest::StatusCode::NOT_FOUND,
+            body: "404 page".to_string(),
+        }
+        .into();
+        assert!(!is_model_missing(&other_404));
+
+        let server_error: anyhow::Error = ApiError {
+            provider: "Ollama",
+            status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            body: "model not found".to_string(),
+        }
+        .into();
+        assert!(!is_model_missing(&server_error));
+    }
+
+    #[tokio::test]
+    async fn test_auto_pull_then_retry() {
+        let mut server = mockito::Server::new_async().await;
+        let missing = server
+            .mock("POST", "/api/generate")
+            .with_status(404)
+            .with_body(r#"{"error":"model \"llama3.2\" not found, try pulling it first"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let pull = server
+            .mock("POST", "/api/pull")
+            .with_status(200)
+            .with_body(
+                "{\"status\":\"pulling manifest\"}\n{\"status\":\"downloading\",\"total\":10,\"completed\":5}\n{\"status\":\"success\"}\n",
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("POST", "/api/generate")
+            .with_status(200)
+            .with_body(r#"{"response": "summary"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                auto_pull: true,
+                ..OllamaConfig::default()
+            },
+            fast_retry(),
+        )
+        .unwrap();
+
+        assert_eq!(provider.summarize("entries").await.unwrap(), "summary");
+        missing.assert_async().await;
+        pull.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[test]
+    fn test_ndjson_accumulator_handles_split_lines() {
+        let mut acc = NdjsonAccumulator::default();
+        let first = acc.push(b"{\"response\":\"{\\\"ti\"}\n{\"resp").unwrap();
+        assert_eq!(first, vec!["{\"ti".to_string()]);
+        let second = acc
+            .push(b"onse\":\"tle\\\": 1}\"}\n{\"response\":\"\",\"done\":true}")
+            .unwrap();
+        assert_eq!(second, vec!["tle\": 1}".to_string()]);
+        assert_eq!(acc.finish().unwrap(), "{\"title\": 1}");
+    }
+
+    #[test]
+    fn test_ndjson_accumulator_surfaces_stream_errors() {
+        let mut acc = NdjsonAccumulator::default();
+        let err = acc.push(b"{\"error\":\"model crashed\"}\n").unwrap_err();
+        assert!(err.to_string().contains("model crashed"));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_request_accumulates_chunks() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/generate")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"stream": true}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_body("{\"response\":\"Hello\"}\n{\"response\":\" world\"}\n{\"response\":\"\",\"done\":true}\n")
+            .create_async()
+            .await;
+
+        let provider = OllamaProvider::new(
+            OllamaConfig {
+                base_url: server.url(),
+                ..OllamaConfig::default()
+            },
+            fast_retry(),
+        )
+        .unwrap();
+
+        let text = provider
+            .call_ollama_json("llama3.2", "prompt", None, true)
+            .await
+            .unwrap();
+        assert_eq!(text, "Hello world");
+        mock.assert_async().await;
+    }
 }
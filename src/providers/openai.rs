@@ -1,20 +1,44 @@
-use crate::config::OpenAiConfig;
-use crate::providers::{sanitize_title, LlmProvider, LlmResponse, TaskItem};
+use crate::config::{OpenAiConfig, RetryConfig};
+use crate::embeddings::Embedder;
+use crate::providers::{
+    build_tags_prompt, capture, check_status, checked_base_url, cleanup_instructions,
+    explain_timeout, format_messages, generate_parsed, is_example_title, json_shape, log_request,
+    normalize_tags, parse_model_ids, protect_code_blocks, restore_code_blocks, shared_client,
+    tags_from_response, title_from_content, unset_extra_options, with_fallback_models, with_retry,
+    Connectivity, LlmProvider, LlmResponse, PromptOptions, RateLimiter, TaskItem, Usage,
+    CHECK_TIMEOUT, DEFAULT_SYSTEM_PROMPT, SUMMARIZE_SYSTEM_PROMPT, TAGS_SYSTEM_PROMPT,
+};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
 
 pub struct OpenAiProvider {
     config: OpenAiConfig,
+    retry: RetryConfig,
+    limiter: RateLimiter,
     client: reqwest::Client,
+    usage: Mutex<Option<Usage>>,
 }
 
 #[derive(Debug, Serialize)]
 struct OpenAiRequest {
     model: String,
     messages: Vec<Message>,
-// temperature: f32, // removed - some models dont support custom temp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    /// The token cap for reasoning models, which reject `max_tokens`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
     response_format: Option<ResponseFormat>,
+    /// `extra_options` the fields above leave unset
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Serialize)]
@@ -24,44 +48,272 @@ struct Message {
 }
 
 #[derive(Debug, Serialize)]
-struct ResponseFormat {
-    #[serde(rename = "type")]
-    type_: String,
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ResponseFormat {
+    /// Any valid JSON object
+    JsonObject,
+    /// Structured outputs: the API enforces the given schema
+    JsonSchema { json_schema: JsonSchema },
+}
+
+#[derive(Debug, Serialize)]
+struct JsonSchema {
+    name: String,
+    strict: bool,
+    schema: serde_json::Value,
+}
+
+/// Model families that accept `json_schema` response formats.
+const STRUCTURED_OUTPUT_MODELS: &[&str] = &["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4"];
+
+/// Reasoning model families, which reject `temperature` and `max_tokens`.
+const REASONING_MODELS: &[&str] = &["gpt-5", "o1", "o3", "o4"];
+
+fn is_reasoning_model(model: &str) -> bool {
+    REASONING_MODELS
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+}
+
+/// Whether `model` supports structured outputs; the first gpt-4o snapshot predates them.
+fn supports_structured_outputs(model: &str) -> bool {
+    model != "gpt-4o-2024-05-13"
+        && STRUCTURED_OUTPUT_MODELS
+            .iter()
+            .any(|prefix| model.starts_with(prefix))
+}
+
+/// Strict schema for a generated entry, mirroring `LlmResponse`.
+fn entry_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "title": { "type": "string" },
+            "content": { "type": "string" },
+            "tags": { "type": "array", "items": { "type": "string" } },
+            "tasks": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "text": { "type": "string" },
+                        "priority": { "type": "string", "enum": ["low", "normal", "high"] },
+                        "due": { "type": ["string", "null"] }
+                    },
+                    "required": ["text", "priority", "due"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["title", "content", "tags", "tasks"],
+        "additionalProperties": false
+    })
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAiResponse {
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Choice {
     message: ResponseMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ResponseMessage {
-    content: String,
+    /// Null when the model refuses
+    #[serde(default)]
+    content: Option<String>,
+    /// Why the model declined, from models with structured outputs
+    #[serde(default)]
+    refusal: Option<String>,
+}
+
+impl Choice {
+    /// The answer text, or an error saying why there is none.
+    fn into_text(self) -> Result<String> {
+        if let Some(refusal) = self.message.refusal.filter(|r| !r.trim().is_empty()) {
+            return Err(anyhow!(
+                "OpenAI declined to process the note: {}\nRephrase the note, or try another model or provider",
+                refusal.trim()
+            ));
+        }
+        if self.finish_reason.as_deref() == Some("content_filter") {
+            return Err(anyhow!(
+                "OpenAI's content filter blocked the response\nRephrase the note, or use a local model (--provider ollama)"
+            ));
+        }
+        self.message
+            .content
+            .ok_or_else(|| anyhow!("OpenAI returned no content"))
+    }
 }
 
+/// `api-version` sent to Azure OpenAI when the config doesn't pin one.
+const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
 impl OpenAiProvider {
-    pub fn new(config: OpenAiConfig) -> Result<Self> {
-        if config.api_key.is_none() {
-            return Err(anyhow!("OpenAI API key not configured. Set OPENAI_API_KEY environment variable or add to config"));
+    pub fn new(mut config: OpenAiConfig, retry: RetryConfig) -> Result<Self> {
+        config.base_url = checked_base_url("openai", &config.base_url)?;
+        if !config.has_credentials() {
+            let var = config.api_key_var();
+            return Err(anyhow!(
+                "OpenAI API key not configured. Set {} environment variable or add to config",
+                var
+            ));
         }
 
+        let client = shared_client(
+            Duration::from_secs(config.timeout_secs),
+            config.proxy.as_deref(),
+        )?;
         Ok(Self {
             config,
-            client: reqwest::Client::new(),
+            limiter: RateLimiter::per_minute(retry.rate_limit_per_min),
+            retry,
+            client,
+            usage: Mutex::new(None),
         })
     }
 
-    fn build_messages(user_input: &str, system_prompt: Option<&str>) -> Vec<Message> {
-        let system_content = system_prompt.unwrap_or(
-            "You are a journal assistant. You clean up note text and extract structured data from it. \
-            NEVER translate — always keep the same language as the input. \
-            Return ONLY valid JSON as instructed.",
-        );
+    /// POST a chat completion request, retrying transient failures, and return the
+    /// first choice's message content with the request's token counts.
+    async fn chat(&self, request: &OpenAiRequest) -> Result<(String, Option<Usage>)> {
+        let api_key = self.config.api_key.as_deref();
+
+        // On Azure a fallback model is another deployment
+        let url = self.deployment_url(&request.model, "chat/completions");
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        log_request("OpenAI", &url, &request.model, request, api_key);
+
+        let response = with_retry(&self.retry, || async {
+            self.limiter.wait().await;
+            let response = capture::send(
+                "OpenAI",
+                self.request(reqwest::Method::POST, &url, api_key)
+                    .json(request),
+            )
+            .await
+            .context("Failed to connect to OpenAI API")?;
+            check_status("OpenAI", response).await
+        })
+        .await
+        .map_err(|e| explain_timeout(e, timeout))?;
+
+        let openai_resp: OpenAiResponse = response
+            .json()
+            .await
+            .context("Failed to parse OpenAI response")
+            .map_err(|e| explain_timeout(e, timeout))?;
+        let text = openai_resp
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No response from OpenAI"))?
+            .into_text()?;
+        Ok((text, openai_resp.usage))
+    }
+
+    /// Endpoint URL for `path`. Azure routes chat completions through the deployment
+    /// and versions every call with `api-version`.
+    fn url(&self, path: &str) -> String {
+        if path == "chat/completions" {
+            return self.deployment_url(&self.config.model, path);
+        }
+        if !self.config.azure {
+            return format!("{}/{}", self.config.base_url, path);
+        }
+        format!(
+            "{}/openai/{}?api-version={}",
+            self.config.base_url,
+            path,
+            self.azure_api_version()
+        )
+    }
+
+    /// Endpoint URL for a model call; on Azure `deployment` names the deployment.
+    fn deployment_url(&self, deployment: &str, path: &str) -> String {
+        if !self.config.azure {
+            return format!("{}/{}", self.config.base_url, path);
+        }
+        format!(
+            "{}/openai/deployments/{}/{}?api-version={}",
+            self.config.base_url,
+            deployment,
+            path,
+            self.azure_api_version()
+        )
+    }
+
+    fn azure_api_version(&self) -> &str {
+        self.config
+            .api_version
+            .as_deref()
+            .unwrap_or(DEFAULT_AZURE_API_VERSION)
+    }
+
+    /// Request builder with auth and the optional organization/project headers.
+    /// Without a key (`require_key = false`) no auth header is sent.
+    fn request(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        api_key: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        let mut builder = self
+            .client
+            .request(method, url)
+            .header("Content-Type", "application/json");
+        if let Some(api_key) = api_key {
+            if self.config.azure {
+                return builder.header("api-key", api_key);
+            }
+            builder = builder.header("Authorization", format!("Bearer {}", api_key));
+        }
+        if let Some(organization) = &self.config.organization {
+            builder = builder.header("OpenAI-Organization", organization);
+        }
+        if let Some(project) = &self.config.project {
+            builder = builder.header("OpenAI-Project", project);
+        }
+        builder
+    }
+
+    /// Lightweight authenticated GET /models to confirm the key works.
+    pub async fn check_connection(&self) -> Result<Connectivity> {
+        let api_key = self.config.api_key.as_deref();
+        let url = self.url("models");
+        let response = self
+            .request(reqwest::Method::GET, &url, api_key)
+            .timeout(CHECK_TIMEOUT)
+            .send()
+            .await
+            .context("Failed to connect to OpenAI API")?;
+        Ok(Connectivity::from_status(response.status()))
+    }
+
+    /// IDs of the models the key can use, from GET /models.
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let api_key = self.config.api_key.as_deref();
+        let response = self
+            .request(reqwest::Method::GET, &self.url("models"), api_key)
+            .timeout(CHECK_TIMEOUT)
+            .send()
+            .await
+            .context("Failed to connect to OpenAI API")?;
+        let body = check_status("OpenAI", response).await?.text().await?;
+        parse_model_ids("OpenAI", &body)
+    }
+
+    fn build_messages(&self, user_input: &str, options: &PromptOptions) -> Vec<Message> {
+        let system_content = options
+            .system_or(self.config.system_prompt.as_deref())
+            .unwrap_or(DEFAULT_SYSTEM_PROMPT);
 
         vec![
             Message {
@@ -71,139 +323,174 @@ impl OpenAiProvider {
             Message {
                 role: "user".to_string(),
                 content: format!(
-                    r#"Process this journal note and return structured JSON.
-
-Input: {input}
-
-Return JSON with these exact fields:
-- "title": 3-5 words from the content, lowercase, hyphen-separated, ends with .md (e.g. "call-jan-q2.md")
-- "content": cleaned text only (fix spelling/grammar if needed), SAME language as input, NO added commentary
-- "tags": 0-3 relevant keywords (lowercase, same language as input)
-- "tasks": array of actionable items extracted from the input
-
-Tasks rules:
-- Extract explicit action items and to-dos from the input
-- Task text MUST be in the SAME language as the input (never translate)
-- Only include tasks that are clearly actionable
-- Keep task text short (1 sentence)
-- priority must be one of: low, normal, high
-- due must be null or ISO date string (YYYY-MM-DD)
-- If the entire note IS an action item (e.g. "Bel Jan over Q2"), include it as a task with the EXACT original text
-- Task text must be verbatim or minimally cleaned — NEVER translate or paraphrase into another language
-- If no tasks, return an empty array
-
-Return ONLY valid JSON, no markdown fences:
-{{"title": "short-descriptive-title.md", "content": "...", "tags": [], "tasks": []}}
-"#,
-                    input = user_input
+                    "Process this journal note and return structured JSON.\n\nInput: {input}\n\n{fields}\n\n{shape}\n{extra}",
+                    input = user_input,
+                    fields = cleanup_instructions(options),
+                    shape = json_shape(),
+                    extra = options.extra_instructions()
                 ),
             },
         ]
     }
 
-    fn has_action_signal(s: &str) -> bool {
-        let s_l = s.to_lowercase();
-        let needles = [
-            "review", "check", "test", "fix", "update", "revise", "refactor",
-            "plan", "prepare", "call", "bel", "mail", "stuur", "maak", "schrijf",
-            "afspraak", "vergadering", "meeting", "rapport", "report",
-        ];
-        needles.iter().any(|n| s_l.contains(n))
+    /// Plain-text request with the same temperature and token cap as generation.
+    fn build_summarize_request(&self, prompt: &str) -> OpenAiRequest {
+        self.build_text_request(SUMMARIZE_SYSTEM_PROMPT, prompt)
     }
-}
 
-#[async_trait]
-impl LlmProvider for OpenAiProvider {
-    async fn generate(&self, prompt: &str, system_prompt: Option<&str>) -> Result<LlmResponse> {
-        let api_key = self
-            .config
-            .api_key
-            .as_ref()
-            .ok_or_else(|| anyhow!("OpenAI API key not set"))?;
-
-        let messages = Self::build_messages(prompt, system_prompt);
+    fn build_tags_request(&self, content: &str, max_tags: usize) -> OpenAiRequest {
+        self.build_text_request(TAGS_SYSTEM_PROMPT, &build_tags_prompt(content, max_tags))
+    }
 
-        let request = OpenAiRequest {
+    /// Plain-text (not JSON mode) request for `prompt` under `system`.
+    fn build_text_request(&self, system: &str, prompt: &str) -> OpenAiRequest {
+        self.with_extra_options(OpenAiRequest {
             model: self.config.model.clone(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: system.to_string(),
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: prompt.to_string(),
+                },
+            ],
+            temperature: Some(self.config.temperature),
+            max_tokens: self.config.max_tokens,
+            max_completion_tokens: None,
+            stop: self.config.stop.clone(),
+            response_format: None, // No JSON mode for summarize
+            extra: serde_json::Map::new(),
+        })
+    }
+
+    /// Entry request to `model`, the configured one or a fallback.
+    fn build_request(&self, model: &str, messages: Vec<Message>) -> OpenAiRequest {
+        self.with_extra_options(OpenAiRequest {
+            model: model.to_string(),
             messages,
-            response_format: Some(ResponseFormat {
-                type_: "json_object".to_string(),
+            temperature: Some(self.config.temperature),
+            max_tokens: self.config.max_tokens,
+            max_completion_tokens: None,
+            stop: self.config.stop.clone(),
+            response_format: Some(if supports_structured_outputs(model) {
+                ResponseFormat::JsonSchema {
+                    json_schema: JsonSchema {
+                        name: "journal_entry".to_string(),
+                        strict: true,
+                        schema: entry_schema(),
+                    },
+                }
+            } else {
+                ResponseFormat::JsonObject
             }),
-        };
+            extra: serde_json::Map::new(),
+        })
+    }
 
-        let url = format!("{}/chat/completions", self.config.base_url);
+    /// `request` fitted to its model, with the configured `extra_options` it doesn't
+    /// set itself. Reasoning models get no temperature and their token cap as
+    /// `max_completion_tokens`.
+    fn with_extra_options(&self, mut request: OpenAiRequest) -> OpenAiRequest {
+        if is_reasoning_model(&request.model) {
+            request.temperature = None;
+            request.max_completion_tokens = request.max_tokens.take();
+        }
+        request.extra = unset_extra_options(&request, &self.config.extra_options);
+        request
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to connect to OpenAI API")?;
+    fn has_action_signal(s: &str) -> bool {
+        let s_l = s.to_lowercase();
+        let needles = [
+            "review",
+            "check",
+            "test",
+            "fix",
+            "update",
+            "revise",
+            "refactor",
+            "plan",
+            "prepare",
+            "call",
+            "bel",
+            "mail",
+            "stuur",
+            "maak",
+            "schrijf",
+            "afspraak",
+            "vergadering",
+            "meeting",
+            "rapport",
+            "report",
+        ];
+        needles.iter().any(|n| s_l.contains(n))
+    }
+}
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("OpenAI API error {}: {}", status, text));
-        }
+/// Add a request's token counts to those of the call it belongs to.
+fn add_usage(total: &Mutex<Option<Usage>>, usage: Option<Usage>) {
+    let mut total = total.lock().unwrap();
+    *total = match (*total, usage) {
+        (Some(total), Some(usage)) => Some(total + usage),
+        (total, usage) => total.or(usage),
+    };
+}
 
-        let openai_resp: OpenAiResponse = response
-            .json()
-            .await
-            .context("Failed to parse OpenAI response")?;
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn prompt_text(&self, prompt: &str, options: &PromptOptions) -> Option<String> {
+        let (protected, _) = protect_code_blocks(prompt);
+        let messages = self.build_messages(&protected, options);
+        Some(format_messages(messages.iter().map(|message| {
+            (message.role.as_str(), message.content.as_str())
+        })))
+    }
 
-        let content = openai_resp
-            .choices
-            .first()
-            .ok_or_else(|| anyhow!("No response from OpenAI"))?
-            .message
-            .content
-            .clone();
-
-        // Strip markdown fences in case the model adds them anyway
-        let json_str = {
-            let trimmed = content.trim();
-            if let Some(start) = trimmed.find("```json") {
-                if let Some(end) = trimmed[start + 7..].find("```") {
-                    trimmed[start + 7..start + 7 + end].trim().to_string()
-                } else {
-                    trimmed.to_string()
-                }
-            } else if let Some(start) = trimmed.find("```") {
-                if let Some(end) = trimmed[start + 3..].find("```") {
-                    trimmed[start + 3..start + 3 + end].trim().to_string()
-                } else {
-                    trimmed.to_string()
-                }
-            } else {
-                trimmed.to_string()
-            }
-        };
+    async fn generate(&self, prompt: &str, options: &PromptOptions) -> Result<LlmResponse> {
+        // Counted per call rather than on the provider, which parallel entries share
+        let usage = Mutex::new(None);
+        let usage = &usage;
+        // Code blocks are swapped for placeholders so the cleanup can't reflow them
+        let (protected, code_blocks) = protect_code_blocks(prompt);
+        let protected = &protected;
 
-        // Parse the JSON response
-        let llm_response: LlmResponse = serde_json::from_str(&json_str)
-            .with_context(|| format!("Failed to parse LLM JSON response: {}", content))?;
+        // Parse the JSON response, tolerating fences and small syntax slips
+        let fallbacks = &self.config.fallback_models;
+        let (mut llm_response, model) =
+            with_fallback_models(&self.config.model, fallbacks, |model| async move {
+                generate_parsed(self.retry.json_retries, options, |options| {
+                    let request =
+                        self.build_request(&model, self.build_messages(protected, &options));
+                    async move {
+                        let (text, request_usage) = self.chat(&request).await?;
+                        add_usage(usage, request_usage);
+                        Ok(text)
+                    }
+                })
+                .await
+            })
+            .await?;
+        llm_response.content = restore_code_blocks(&llm_response.content, &code_blocks);
 
-        // Sanitize the title; guard against the model returning the literal example placeholder
+        // Guard against the model returning the literal example placeholder
         let raw_title = &llm_response.title;
-        let title = if raw_title.trim().is_empty()
-            || raw_title == "name.md"
-            || raw_title == "title.md"
-            || raw_title == "short-descriptive-title.md"
-        {
+        let title = if raw_title.trim().is_empty() {
+            title_from_content(&llm_response.content)
+        } else if is_example_title(raw_title) {
             // Derive from the first few words of the original prompt
             let words: Vec<&str> = prompt.split_whitespace().take(5).collect();
-            sanitize_title(&words.join(" "))
+            words.join(" ")
         } else {
-            sanitize_title(raw_title)
+            raw_title.clone()
         };
 
         // Tasks fallback: if the model returned no tasks but the note looks like actionable work
         let mut tasks = llm_response.tasks;
         if tasks.is_empty() && Self::has_action_signal(&llm_response.content) {
-            let first_line = llm_response.content
+            let first_line = llm_response
+                .content
                 .lines()
                 .map(|l| l.trim())
                 .find(|l| !l.is_empty() && !l.starts_with('#'))
@@ -218,72 +505,87 @@ impl LlmProvider for OpenAiProvider {
             }
         }
 
+        let usage = *usage.lock().unwrap();
         Ok(LlmResponse {
             title,
             content: llm_response.content,
-            tags: llm_response.tags,
+            tags: options.limit_tags(normalize_tags(llm_response.tags)),
             tasks,
+            model: Some(model),
+            usage,
         })
     }
 
     async fn summarize(&self, prompt: &str) -> Result<String> {
-        let api_key = self
-            .config
-            .api_key
-            .as_ref()
-            .ok_or_else(|| anyhow!("OpenAI API key not set"))?;
+        let (text, usage) = self.chat(&self.build_summarize_request(prompt)).await?;
+        *self.usage.lock().unwrap() = usage;
+        Ok(text)
+    }
 
-        let messages = vec![
-            Message {
-                role: "system".to_string(),
-                content: "You are a helpful assistant that summarizes journal entries. Be concise and highlight key points. IMPORTANT: Always respond in the SAME language as the journal entries - never translate to another language.".to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            },
-        ];
+    async fn suggest_tags(&self, content: &str, max_tags: usize) -> Result<Vec<String>> {
+        let (raw, usage) = self
+            .chat(&self.build_tags_request(content, max_tags))
+            .await?;
+        *self.usage.lock().unwrap() = usage;
+        tags_from_response(&raw, max_tags)
+    }
 
-        let request = OpenAiRequest {
-            model: self.config.model.clone(),
-            messages,
-            response_format: None, // No JSON mode for summarize
-        };
+    async fn is_available(&self) -> bool {
+        self.config.has_credentials()
+    }
 
-        let url = format!("{}/chat/completions", self.config.base_url);
+    fn last_usage(&self) -> Option<Usage> {
+        *self.usage.lock().unwrap()
+    }
+}
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
+#[derive(Debug, Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+#[async_trait]
+impl Embedder for OpenAiProvider {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let api_key = self.config.api_key.as_deref();
+        let url = self.deployment_url(&self.config.embedding_model, "embeddings");
+        let body = serde_json::json!({
+            "model": self.config.embedding_model,
+            "input": text,
+        });
+
+        let response = with_retry(&self.retry, || async {
+            self.limiter.wait().await;
+            let response = capture::send(
+                "OpenAI",
+                self.request(reqwest::Method::POST, &url, api_key)
+                    .json(&body),
+            )
             .await
             .context("Failed to connect to OpenAI API")?;
+            check_status("OpenAI", response).await
+        })
+        .await?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("OpenAI API error {}: {}", status, text));
-        }
-
-        let openai_resp: OpenAiResponse = response
+        let embeddings: EmbeddingsResponse = response
             .json()
             .await
-            .context("Failed to parse OpenAI response")?;
-
-        Ok(openai_resp
-            .choices
-            .first()
-            .ok_or_else(|| anyhow!("No response from OpenAI"))?
-            .message
-            .content
-            .clone())
+            .context("Failed to parse OpenAI embeddings response")?;
+        embeddings
+            .data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .ok_or_else(|| anyhow!("No embedding from OpenAI"))
     }
 
-    fn is_available(&self) -> bool {
-        self.config.api_key.is_some()
+    fn embedding_model(&self) -> &str {
+        &self.config.embedding_model
     }
 }
 
@@ -291,18 +593,681 @@ impl LlmProvider for OpenAiProvider {
 mod tests {
     use super::*;
 
+    fn provider() -> OpenAiProvider {
+        OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_new_requires_key_unless_disabled() {
+        assert!(OpenAiProvider::new(OpenAiConfig::default(), RetryConfig::default()).is_err());
+
+        let local = OpenAiConfig {
+            base_url: "http://localhost:8080/v1".to_string(),
+            require_key: false,
+            ..OpenAiConfig::default()
+        };
+        assert!(OpenAiProvider::new(local, RetryConfig::default()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_keyless_server_gets_no_auth_header() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/models")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_body(r#"{"data": [{"id": "qwen2.5-7b-instruct"}]}"#)
+            .create_async()
+            .await;
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                base_url: server.url(),
+                require_key: false,
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+
+        assert!(provider.is_available().await);
+        assert_eq!(
+            provider.list_models().await.unwrap(),
+            vec!["qwen2.5-7b-instruct"]
+        );
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_list_models() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/models")
+            .match_header("authorization", "Bearer test-key")
+            .with_body(r#"{"object": "list", "data": [{"id": "gpt-4o-mini", "object": "model"}]}"#)
+            .create_async()
+            .await;
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                base_url: server.url(),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(provider.list_models().await.unwrap(), vec!["gpt-4o-mini"]);
+    }
+
+    #[tokio::test]
+    async fn test_generate_without_tags_discards_model_tags() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::Regex("empty .*tags.* array".to_string()))
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "{\"title\": \"flights.md\", \"content\": \"Booked flights.\", \"tags\": [\"travel\", \"lisbon\"]}"}}]}"#,
+            )
+            .create_async()
+            .await;
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                base_url: server.url(),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+
+        let options = PromptOptions {
+            no_tags: true,
+            ..PromptOptions::default()
+        };
+        let response = provider.generate("booked flights", &options).await.unwrap();
+        assert!(response.tags.is_empty());
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_generate_titles_untitled_entry_from_content() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/chat/completions")
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "{\"title\": \" \", \"content\": \"Booked flights to Lisbon. Hotel next.\", \"tags\": []}"}}]}"#,
+            )
+            .create_async()
+            .await;
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                base_url: server.url(),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+
+        let response = provider
+            .generate(
+                "booked flights lisbon, hotel next",
+                &PromptOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.title, "Booked flights to Lisbon");
+    }
+
+    #[test]
+    fn test_build_messages_mentions_tag_count() {
+        let options = PromptOptions {
+            max_tags: 4,
+            ..PromptOptions::default()
+        };
+        let messages = provider().build_messages("Test input", &options);
+        assert!(messages[1]
+            .content
+            .contains("\"tags\": 0-4 relevant keywords"));
+
+        let messages = provider().build_messages("Test input", &PromptOptions::default());
+        assert!(messages[1]
+            .content
+            .contains("\"tags\": a few relevant keywords"));
+
+        // Without tags there is no count to ask for
+        let options = PromptOptions {
+            max_tags: 4,
+            no_tags: true,
+            ..PromptOptions::default()
+        };
+        let messages = provider().build_messages("Test input", &options);
+        assert!(!messages[1].content.contains("relevant keywords"));
+        assert!(messages[1]
+            .content
+            .contains("return an empty \"tags\" array"));
+    }
+
+    #[test]
+    fn test_build_messages_with_cleanup_level() {
+        let options = PromptOptions {
+            cleanup: crate::providers::Cleanup::Light,
+            ..PromptOptions::default()
+        };
+        let messages = provider().build_messages("Test input", &options);
+        assert!(messages[1].content.contains("Only fix obvious typos"));
+    }
+
+    #[test]
+    fn test_build_messages_with_tone_keeps_language_rule() {
+        let options = PromptOptions {
+            tone: crate::providers::Tone::Casual,
+            ..PromptOptions::default()
+        };
+        let messages = provider().build_messages("Test input", &options);
+        assert!(messages[1].content.contains("casual, conversational tone"));
+        assert!(messages[1].content.contains("NEVER translate"));
+        assert!(messages[0].content.contains("NEVER translate"));
+    }
+
+    fn first_choice(body: &str) -> Choice {
+        let response: OpenAiResponse = serde_json::from_str(body).unwrap();
+        response.choices.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_refusal_is_a_clear_error() {
+        let choice = first_choice(
+            r#"{"choices": [{"message": {"role": "assistant", "content": null,
+                "refusal": "I'm sorry, I can't help with that."}, "finish_reason": "stop"}]}"#,
+        );
+        let err = choice.into_text().unwrap_err().to_string();
+        assert!(
+            err.contains("OpenAI declined to process the note: I'm sorry, I can't help with that.")
+        );
+        assert!(err.contains("Rephrase the note"));
+    }
+
+    #[test]
+    fn test_content_filter_is_a_clear_error() {
+        let choice = first_choice(
+            r#"{"choices": [{"message": {"role": "assistant", "content": ""},
+                "finish_reason": "content_filter"}]}"#,
+        );
+        let err = choice.into_text().unwrap_err().to_string();
+        assert!(err.contains("content filter blocked the response"));
+        assert!(err.contains("--provider ollama"));
+    }
+
+    #[test]
+    fn test_normal_choice_returns_content() {
+        let choice = first_choice(
+            r#"{"choices": [{"message": {"role": "assistant", "content": "{}", "refusal": null},
+                "finish_reason": "stop"}]}"#,
+        );
+        assert_eq!(choice.into_text().unwrap(), "{}");
+    }
+
     #[test]
     fn test_build_messages() {
-        let messages = OpenAiProvider::build_messages("Test input", None);
+        let messages = provider().build_messages("Test input", &PromptOptions::default());
         assert_eq!(messages.len(), 2);
         assert_eq!(messages[0].role, "system");
         assert_eq!(messages[1].role, "user");
         assert!(messages[1].content.contains("Test input"));
     }
 
+    #[test]
+    fn test_request_uses_configured_temperature() {
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                temperature: 1.5,
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let messages = provider.build_messages("Test", &PromptOptions::default());
+        let request = provider.build_request(&provider.config.model, messages);
+        let body = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(body["temperature"].as_f64().unwrap() as f32, 1.5);
+        assert!(body.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn test_reasoning_model_request_drops_temperature() {
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                model: "o3-mini".to_string(),
+                max_tokens: Some(512),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let messages = provider.build_messages("Test", &PromptOptions::default());
+        let body =
+            serde_json::to_value(provider.build_request(&provider.config.model, messages)).unwrap();
+
+        assert_eq!(body["model"], "o3-mini");
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("max_tokens").is_none());
+        assert_eq!(body["max_completion_tokens"], 512);
+        assert_eq!(body["response_format"]["type"], "json_schema");
+
+        let body = serde_json::to_value(provider.build_summarize_request("Summarize")).unwrap();
+        assert!(body.get("temperature").is_none());
+        assert_eq!(body["max_completion_tokens"], 512);
+    }
+
+    #[test]
+    fn test_extra_options_are_top_level_fields() {
+        let mut extra_options = serde_json::Map::new();
+        extra_options.insert("top_p".to_string(), serde_json::json!(0.8));
+        extra_options.insert("model".to_string(), serde_json::json!("other"));
+        extra_options.insert("max_tokens".to_string(), serde_json::json!(64));
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                extra_options,
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let messages = provider.build_messages("Test", &PromptOptions::default());
+        let body =
+            serde_json::to_value(provider.build_request(&provider.config.model, messages)).unwrap();
+
+        assert_eq!(body["top_p"].as_f64().unwrap() as f32, 0.8);
+        assert_eq!(body["model"], "gpt-4o-mini");
+        // Unset max_tokens is left to extra_options
+        assert_eq!(body["max_tokens"], 64);
+    }
+
+    #[test]
+    fn test_summarize_request_uses_configured_temperature_and_max_tokens() {
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                temperature: 1.5,
+                max_tokens: Some(512),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let body = serde_json::to_value(provider.build_summarize_request("entries")).unwrap();
+
+        assert_eq!(body["temperature"].as_f64().unwrap() as f32, 1.5);
+        assert_eq!(body["max_tokens"], 512);
+        assert_eq!(body["messages"][0]["content"], SUMMARIZE_SYSTEM_PROMPT);
+        assert!(body["response_format"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_suggest_tags_uses_tags_system_prompt() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "messages": [{"role": "system", "content": TAGS_SYSTEM_PROMPT}],
+            })))
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "{\"tags\": [\"Work\", \"q2\", \"budget\"]}"}}]}"#,
+            )
+            .create_async()
+            .await;
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                base_url: server.url(),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+
+        let tags = provider.suggest_tags("Call Jan about Q2", 2).await.unwrap();
+        assert_eq!(tags, vec!["work", "q2"]);
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_stop_sequences_in_request() {
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                stop: vec!["\n\n\n".to_string(), "</s>".to_string()],
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let messages = provider.build_messages("Test", &PromptOptions::default());
+        let body =
+            serde_json::to_value(provider.build_request(&provider.config.model, messages)).unwrap();
+        assert_eq!(body["stop"], serde_json::json!(["\n\n\n", "</s>"]));
+        let body = serde_json::to_value(provider.build_summarize_request("entries")).unwrap();
+        assert_eq!(body["stop"], serde_json::json!(["\n\n\n", "</s>"]));
+
+        assert!(request_body("gpt-4o-mini").get("stop").is_none());
+    }
+
+    fn request_body(model: &str) -> serde_json::Value {
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                model: model.to_string(),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let messages = provider.build_messages("Test", &PromptOptions::default());
+        let request = provider.build_request(&provider.config.model, messages);
+        serde_json::to_value(&request).unwrap()
+    }
+
+    #[test]
+    fn test_structured_outputs_schema_is_strict() {
+        let body = request_body("gpt-4o-mini");
+        let format = &body["response_format"];
+        assert_eq!(format["type"], "json_schema");
+        assert_eq!(format["json_schema"]["strict"], true);
+        assert_eq!(
+            format["json_schema"]["schema"]["required"],
+            serde_json::json!(["title", "content", "tags", "tasks"])
+        );
+        assert_eq!(
+            format["json_schema"]["schema"]["additionalProperties"],
+            false
+        );
+    }
+
+    #[test]
+    fn test_older_models_fall_back_to_json_object() {
+        for model in ["gpt-3.5-turbo", "gpt-4", "gpt-4o-2024-05-13"] {
+            let body = request_body(model);
+            assert_eq!(body["response_format"]["type"], "json_object", "{}", model);
+            assert!(body["response_format"].get("json_schema").is_none());
+        }
+    }
+
+    #[test]
+    fn test_request_includes_configured_max_tokens() {
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                max_tokens: Some(512),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let messages = provider.build_messages("Test", &PromptOptions::default());
+        let request = provider.build_request(&provider.config.model, messages);
+        let body = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(body["max_tokens"], 512);
+    }
+
+    #[test]
+    fn test_organization_and_project_headers() {
+        let config = OpenAiConfig {
+            api_key: Some("test-key".to_string()),
+            ..OpenAiConfig::default()
+        };
+        let provider = OpenAiProvider::new(config.clone(), RetryConfig::default()).unwrap();
+        let request = provider
+            .request(
+                reqwest::Method::POST,
+                "http://localhost/v1",
+                Some("test-key"),
+            )
+            .build()
+            .unwrap();
+        assert!(request.headers().get("OpenAI-Organization").is_none());
+        assert!(request.headers().get("OpenAI-Project").is_none());
+
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                organization: Some("org-123".to_string()),
+                project: Some("proj_456".to_string()),
+                ..config
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let request = provider
+            .request(
+                reqwest::Method::POST,
+                "http://localhost/v1",
+                Some("test-key"),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["OpenAI-Organization"], "org-123");
+        assert_eq!(request.headers()["OpenAI-Project"], "proj_456");
+        assert_eq!(request.headers()["Authorization"], "Bearer test-key");
+    }
+
+    #[test]
+    fn test_new_checks_base_url() {
+        let with_url = |base_url: &str| {
+            OpenAiProvider::new(
+                OpenAiConfig {
+                    base_url: base_url.to_string(),
+                    api_key: Some("sk-test".to_string()),
+                    ..OpenAiConfig::default()
+                },
+                RetryConfig::default(),
+            )
+        };
+        let err = with_url("api.openai.com/v1").err().unwrap().to_string();
+        assert!(
+            err.contains("[openai] base_url 'api.openai.com/v1'"),
+            "{}",
+            err
+        );
+
+        let provider = with_url("http://localhost:8080/v1/").unwrap();
+        assert_eq!(provider.url("models"), "http://localhost:8080/v1/models");
+        let provider = with_url("https://api.openai.com/v1").unwrap();
+        assert_eq!(
+            provider.url("chat/completions"),
+            "https://api.openai.com/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    fn test_azure_url_and_header() {
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                base_url: "https://journal.openai.azure.com".to_string(),
+                model: "gpt-4o-mini-prod".to_string(),
+                api_key: Some("azure-key".to_string()),
+                azure: true,
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            provider.url("chat/completions"),
+            "https://journal.openai.azure.com/openai/deployments/gpt-4o-mini-prod/chat/completions?api-version=2024-10-21"
+        );
+        assert_eq!(
+            provider.url("models"),
+            "https://journal.openai.azure.com/openai/models?api-version=2024-10-21"
+        );
+
+        let request = provider
+            .request(
+                reqwest::Method::POST,
+                &provider.url("chat/completions"),
+                Some("azure-key"),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["api-key"], "azure-key");
+        assert!(request.headers().get("Authorization").is_none());
+    }
+
+    #[test]
+    fn test_standard_url_unchanged() {
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                api_version: Some("2024-10-21".to_string()),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            provider.url("chat/completions"),
+            "https://api.openai.com/v1/chat/completions"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usage_sums_json_retries() {
+        let mut server = mockito::Server::new_async().await;
+        let strict = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::Regex("not valid JSON".to_string()))
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "{\"title\": \"lunch.md\", \"content\": \"Lunch.\"}"}}],
+                    "usage": {"prompt_tokens": 120, "completion_tokens": 20}}"#,
+            )
+            .create_async()
+            .await;
+        let prose = server
+            .mock("POST", "/chat/completions")
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "Sure, lunch."}}],
+                    "usage": {"prompt_tokens": 100, "completion_tokens": 5}}"#,
+            )
+            .expect(2)
+            .create_async()
+            .await;
+        let provider = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                base_url: server.url(),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+
+        let response = provider
+            .generate("Lunch", &PromptOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                prompt_tokens: 220,
+                completion_tokens: 25,
+            })
+        );
+        strict.assert_async().await;
+
+        // A summary reports its own request only
+        provider.summarize("entries").await.unwrap();
+        assert_eq!(
+            provider.last_usage(),
+            Some(Usage {
+                prompt_tokens: 100,
+                completion_tokens: 5,
+            })
+        );
+        prose.assert_async().await;
+    }
+
+    #[test]
+    fn test_response_usage_parsing() {
+        let body = r#"{
+            "choices": [{"message": {"content": "hi"}}],
+            "usage": {"prompt_tokens": 120, "completion_tokens": 45, "total_tokens": 165}
+        }"#;
+        let response: OpenAiResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                prompt_tokens: 120,
+                completion_tokens: 45,
+            })
+        );
+
+        let response: OpenAiResponse =
+            serde_json::from_str(r#"{"choices": [{"message": {"content": "hi"}}]}"#).unwrap();
+        assert_eq!(response.usage, None);
+    }
+
     #[test]
     fn test_custom_system_prompt() {
-        let messages = OpenAiProvider::build_messages("Test", Some("Custom prompt"));
+        let options = PromptOptions {
+            system: Some("Custom prompt".to_string()),
+            ..PromptOptions::default()
+        };
+        let messages = provider().build_messages("Test", &options);
         assert_eq!(messages[0].content, "Custom prompt");
     }
+
+    #[test]
+    fn test_system_prompt_precedence() {
+        let configured = OpenAiProvider::new(
+            OpenAiConfig {
+                api_key: Some("test-key".to_string()),
+                system_prompt: Some("Answer tersely.".to_string()),
+                ..OpenAiConfig::default()
+            },
+            RetryConfig::default(),
+        )
+        .unwrap();
+        let per_request = PromptOptions {
+            system: Some("Custom prompt".to_string()),
+            ..PromptOptions::default()
+        };
+
+        // The request's prompt beats the config, which beats the built-in
+        assert_eq!(
+            configured.build_messages("Test", &per_request)[0].content,
+            "Custom prompt"
+        );
+        assert_eq!(
+            configured.build_messages("Test", &PromptOptions::default())[0].content,
+            "Answer tersely."
+        );
+        assert_eq!(
+            provider().build_messages("Test", &PromptOptions::default())[0].content,
+            DEFAULT_SYSTEM_PROMPT
+        );
+    }
+
+    #[test]
+    fn test_template_in_messages() {
+        let options = PromptOptions {
+            template: Some("Add a ## Attendees section".to_string()),
+            ..PromptOptions::default()
+        };
+        let messages = provider().build_messages("Test", &options);
+        assert!(messages[1].content.contains("## Attendees"));
+        assert!(
+            !provider().build_messages("Test", &PromptOptions::default())[1]
+                .content
+                .contains("template")
+        );
+    }
 }
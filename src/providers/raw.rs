@@ -0,0 +1,65 @@
+use crate::providers::{first_line_title, LlmProvider, LlmResponse, PromptOptions, TitleOptions};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+/// Passthrough "provider" for `--raw` / `provider = "none"`: files the note verbatim,
+/// titled after its first line, without calling a model.
+pub struct RawProvider {
+    title: TitleOptions,
+}
+
+impl RawProvider {
+    pub fn new(title: TitleOptions) -> Self {
+        Self { title }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RawProvider {
+    async fn generate(&self, prompt: &str, _options: &PromptOptions) -> Result<LlmResponse> {
+        Ok(LlmResponse {
+            title: first_line_title(prompt, &self.title),
+            content: prompt.to_string(),
+            tags: vec![],
+            tasks: vec![],
+            model: None,
+            usage: None,
+        })
+    }
+
+    async fn summarize(&self, _prompt: &str) -> Result<String> {
+        Err(anyhow!(
+            "Summaries need a model. Use 'ollama', 'openai' or 'anthropic' as the provider"
+        ))
+    }
+
+    async fn suggest_tags(&self, _content: &str, _max_tags: usize) -> Result<Vec<String>> {
+        Err(anyhow!(
+            "Tag suggestions need a model. Use 'ollama', 'openai' or 'anthropic' as the provider"
+        ))
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_raw_keeps_content_verbatim() {
+        let provider = RawProvider::new(TitleOptions::default());
+        let note = "Call Jan about Q2\n\n  - budget  \n";
+        let response = provider
+            .generate(note, &PromptOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.title, "call-jan-about-q2.md");
+        assert_eq!(response.content, note);
+        assert!(response.tags.is_empty());
+        assert!(provider.summarize("x").await.is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use std::io::{BufRead, Write};
+
+use crate::config::Config;
+use crate::format_preview;
+use crate::providers::{
+    build_refine_prompt, LlmProvider, LlmResponse, PromptOptions, REFINE_SYSTEM_PROMPT,
+};
+use crate::spinner;
+
+/// How a `--refine` session ended.
+#[derive(Debug)]
+pub enum Outcome {
+    Save(LlmResponse),
+    Quit,
+}
+
+/// Show the entry and rework it with each instruction read from `reader` until the user
+/// types `save` or `quit` (EOF quits). The title stays as generated; a failed
+/// refinement keeps the previous version.
+pub async fn refine(
+    config: &Config,
+    provider: &dyn LlmProvider,
+    mut current: LlmResponse,
+    reader: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<Outcome> {
+    let options = PromptOptions {
+        system: Some(REFINE_SYSTEM_PROMPT.to_string()),
+        ..config.prompt_options()?
+    };
+
+    loop {
+        write!(out, "{}", format_preview(&current))?;
+        write!(out, "\nRefine (or 'save' / 'quit')> ")?;
+        out.flush()?;
+
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            writeln!(out)?;
+            return Ok(Outcome::Quit);
+        }
+        match line.trim() {
+            "" => continue,
+            "save" => return Ok(Outcome::Save(current)),
+            "quit" => return Ok(Outcome::Quit),
+            instruction => {
+                let prompt = build_refine_prompt(&current, instruction);
+                let refined = spinner::with_spinner(
+                    config,
+                    "Refining...",
+                    provider.generate(&prompt, &options),
+                )
+                .await;
+                match refined {
+                    Ok(refined) => {
+                        current = LlmResponse {
+                            title: std::mem::take(&mut current.title),
+                            ..refined
+                        }
+                    }
+                    Err(e) => writeln!(out, "Error: {:#}", e)?,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    fn entry() -> LlmResponse {
+        LlmResponse {
+            title: "team-sync.md".to_string(),
+            content: "Discussed Q2 at length.".to_string(),
+            tags: vec!["work".to_string()],
+            tasks: vec![],
+            model: None,
+            usage: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refine_then_save() {
+        let config = Config {
+            quiet: true,
+            ..Config::default()
+        };
+        let provider = MockProvider::new();
+        let mut input = "make it shorter\n\nsave\n".as_bytes();
+        let mut out = Vec::new();
+
+        let outcome = refine(&config, &provider, entry(), &mut input, &mut out)
+            .await
+            .unwrap();
+
+        let prompts = provider.prompts();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("Discussed Q2 at length."));
+        assert!(prompts[0].contains("Instruction: make it shorter"));
+        let Outcome::Save(saved) = outcome else {
+            panic!("expected save");
+        };
+        // The mock echoes the prompt back as content; the title is kept
+        assert_eq!(saved.title, "team-sync.md");
+        assert!(saved.content.contains("Instruction: make it shorter"));
+        assert_eq!(
+            String::from_utf8(out)
+                .unwrap()
+                .matches("=== Preview ===")
+                .count(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refine_quit_and_eof() {
+        let config = Config::default();
+        let provider = MockProvider::new();
+        for input in ["quit\n", ""] {
+            let outcome = refine(
+                &config,
+                &provider,
+                entry(),
+                &mut input.as_bytes(),
+                &mut Vec::new(),
+            )
+            .await
+            .unwrap();
+            assert!(matches!(outcome, Outcome::Quit));
+        }
+        assert!(provider.prompts().is_empty());
+    }
+}
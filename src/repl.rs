@@ -0,0 +1,165 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use crate::config::Config;
+use crate::providers::{append_tags, LlmProvider};
+use crate::{generate_entry, print_preview, save_response, SavedEntry};
+
+#[derive(Debug, PartialEq, Eq)]
+enum ReplCommand {
+    Quit,
+    /// Toggle preview mode (generate and show, but don't save)
+    Preview,
+    /// Replace the tags added to every following entry
+    Tags(Vec<String>),
+    /// Delete the most recently saved entry and its todos
+    Undo,
+    Unknown(String),
+}
+
+/// Parse a `:command` line. Returns `None` for regular note content.
+fn parse_command(line: &str) -> Option<ReplCommand> {
+    let rest = line.strip_prefix(':')?;
+    let (name, arg) = match rest.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (rest, ""),
+    };
+
+    Some(match name {
+        "quit" | "q" | "exit" => ReplCommand::Quit,
+        "preview" => ReplCommand::Preview,
+        "tags" => ReplCommand::Tags(
+            arg.split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect(),
+        ),
+        "undo" => ReplCommand::Undo,
+        other => ReplCommand::Unknown(other.to_string()),
+    })
+}
+
+fn undo(saved: SavedEntry) -> Result<()> {
+    let Some(path) = saved.path else {
+        return Err(anyhow::anyhow!(
+            "Location of the last entry is unknown, nothing removed"
+        ));
+    };
+    fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    for todo in &saved.todos {
+        fs::remove_file(todo).with_context(|| format!("Failed to remove {}", todo.display()))?;
+    }
+    println!("Removed {}", path.display());
+    if !saved.todos.is_empty() {
+        println!("Removed {} todo(s)", saved.todos.len());
+    }
+    Ok(())
+}
+
+/// Read notes from stdin line by line, generating and saving one entry per line.
+///
+/// The provider is created once and reused for every note.
+pub async fn run(config: &Config, provider: &dyn LlmProvider) -> Result<()> {
+    println!(
+        "journal-ai REPL using {}. One note per line.",
+        config.provider
+    );
+    println!("Commands: :preview, :tags a,b, :undo, :quit");
+
+    let stdin = io::stdin();
+    let mut preview = false;
+    let mut extra_tags: Vec<String> = Vec::new();
+    let mut history: Vec<SavedEntry> = Vec::new();
+
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            break;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match parse_command(line) {
+            Some(ReplCommand::Quit) => break,
+            Some(ReplCommand::Preview) => {
+                preview = !preview;
+                println!("Preview mode {}", if preview { "on" } else { "off" });
+            }
+            Some(ReplCommand::Tags(tags)) => {
+                if tags.is_empty() {
+                    println!("Extra tags cleared");
+                } else {
+                    println!("Extra tags: {}", tags.join(", "));
+                }
+                extra_tags = tags;
+            }
+            Some(ReplCommand::Undo) => match history.pop() {
+                Some(saved) => {
+                    if let Err(e) = undo(saved) {
+                        eprintln!("Error: {:#}", e);
+                    }
+                }
+                None => println!("Nothing to undo"),
+            },
+            Some(ReplCommand::Unknown(name)) => eprintln!("Unknown command: :{}", name),
+            None => {
+                let mut response = match generate_entry(provider, config, line).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        eprintln!("Error: {:#}", e);
+                        continue;
+                    }
+                };
+                response.tags = append_tags(response.tags, &extra_tags);
+
+                if preview {
+                    print_preview(&response);
+                    continue;
+                }
+
+                match save_response(config, &response, None, &mut io::stdout()) {
+                    Ok(saved) => history.push(saved),
+                    Err(e) => eprintln!("Error: {:#}", e),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command_content_is_not_a_command() {
+        assert_eq!(parse_command("Met with team"), None);
+    }
+
+    #[test]
+    fn test_parse_command_meta_commands() {
+        assert_eq!(parse_command(":quit"), Some(ReplCommand::Quit));
+        assert_eq!(parse_command(":preview"), Some(ReplCommand::Preview));
+        assert_eq!(parse_command(":undo"), Some(ReplCommand::Undo));
+        assert_eq!(
+            parse_command(":tags work, q2 ,"),
+            Some(ReplCommand::Tags(vec![
+                "work".to_string(),
+                "q2".to_string()
+            ]))
+        );
+        assert_eq!(parse_command(":tags"), Some(ReplCommand::Tags(vec![])));
+        assert_eq!(
+            parse_command(":foo"),
+            Some(ReplCommand::Unknown("foo".to_string()))
+        );
+    }
+}
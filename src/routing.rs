@@ -0,0 +1,55 @@
+use crate::config::Config;
+
+/// The provider `provider = "auto"` uses for a note of `len` characters: Ollama for
+/// notes under `auto_threshold_chars`, the cloud provider for longer ones. With no cloud
+/// provider configured everything stays on Ollama.
+pub fn choose_provider(len: usize, config: &Config) -> String {
+    match cloud_provider(config) {
+        Some(cloud) if len >= config.auto_threshold_chars => cloud.to_string(),
+        _ => "ollama".to_string(),
+    }
+}
+
+/// The first cloud provider that can be called: OpenAI, then Anthropic.
+fn cloud_provider(config: &Config) -> Option<&'static str> {
+    config
+        .callable_providers()
+        .into_iter()
+        .find(|&name| name != "ollama")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_keys(openai_key: bool, anthropic_key: bool) -> Config {
+        let mut config = Config {
+            provider: "auto".to_string(),
+            auto_threshold_chars: 1000,
+            ..Config::default()
+        };
+        config.openai.api_key = openai_key.then(|| "sk-test".to_string());
+        config.anthropic.api_key = anthropic_key.then(|| "sk-ant-test".to_string());
+        config
+    }
+
+    #[test]
+    fn test_threshold_splits_local_and_cloud() {
+        let config = with_keys(true, true);
+        assert_eq!(choose_provider(0, &config), "ollama");
+        assert_eq!(choose_provider(999, &config), "ollama");
+        assert_eq!(choose_provider(1000, &config), "openai");
+        assert_eq!(choose_provider(50_000, &config), "openai");
+    }
+
+    #[test]
+    fn test_only_one_provider_configured() {
+        let config = with_keys(false, false);
+        assert_eq!(choose_provider(999, &config), "ollama");
+        assert_eq!(choose_provider(1000, &config), "ollama");
+
+        let config = with_keys(false, true);
+        assert_eq!(choose_provider(999, &config), "ollama");
+        assert_eq!(choose_provider(1000, &config), "anthropic");
+    }
+}
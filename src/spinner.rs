@@ -0,0 +1,55 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::future::Future;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+use crate::config::Config;
+
+/// Whether to animate a spinner on stderr. Scripts (stderr not a terminal), `--quiet`
+/// and `--json` get none, and neither does Ollama streaming, which prints tokens there.
+fn should_show(stderr_is_terminal: bool, quiet: bool, streaming: bool) -> bool {
+    stderr_is_terminal && !quiet && !streaming
+}
+
+/// Await `fut` behind a spinner reading `message`, cleared once it resolves either way.
+pub async fn with_spinner<F: Future>(config: &Config, message: &str, fut: F) -> F::Output {
+    let streaming = config.provider == "ollama" && config.ollama.stream;
+    if !should_show(std::io::stderr().is_terminal(), config.quiet, streaming) {
+        return fut.await;
+    }
+
+    let spinner = ProgressBar::new_spinner();
+    if let Ok(style) = ProgressStyle::with_template("{spinner} {msg} {elapsed}") {
+        spinner.set_style(style);
+    }
+    spinner.set_message(message.to_string());
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    let output = fut.await;
+    spinner.finish_and_clear();
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spinner_only_on_an_interactive_terminal() {
+        assert!(should_show(true, false, false));
+        // Piped stderr, --quiet (which --json implies) and streaming all suppress it
+        assert!(!should_show(false, false, false));
+        assert!(!should_show(true, true, false));
+        assert!(!should_show(true, false, true));
+    }
+
+    #[tokio::test]
+    async fn test_with_spinner_passes_output_through() {
+        let config = Config {
+            quiet: true,
+            ..Config::default()
+        };
+        let result: Result<u8, &str> =
+            with_spinner(&config, "Waiting", async { Err("boom") }).await;
+        assert_eq!(result, Err("boom"));
+    }
+}
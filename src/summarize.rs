@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::journal;
+
+/// Date of an entry file, taken from its name when possible.
+///
+/// Recognizes a `YYYY-MM-DD` filename prefix and the file-journal layout
+/// `YYYY/MM/dd-HHMMSS-title.md`; otherwise falls back to the modification time.
+pub fn entry_date(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?;
+
+    if let Some(date) = name
+        .get(..10)
+        .and_then(|p| NaiveDate::parse_from_str(p, "%Y-%m-%d").ok())
+    {
+        return Some(date);
+    }
+
+    let day = name.get(..2)?;
+    let month_dir = path.parent()?;
+    let month = month_dir.file_name()?.to_str()?;
+    let year = month_dir.parent()?.file_name()?.to_str()?;
+    if let Ok(date) = NaiveDate::parse_from_str(&format!("{}-{}-{}", year, month, day), "%Y-%m-%d")
+    {
+        return Some(date);
+    }
+
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(DateTime::<Local>::from(modified).date_naive())
+}
+
+/// Files to summarize: the file itself, or every entry (ending in `.<extension>`) under
+/// a directory.
+///
+/// `since` keeps only directory entries dated on or after that day.
+pub fn collect_files(
+    path: &Path,
+    since: Option<NaiveDate>,
+    extension: &str,
+) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        return Err(anyhow!("{} does not exist", path.display()));
+    }
+
+    let mut files = journal::list_entries(path, extension)?;
+    if let Some(since) = since {
+        files.retain(|f| entry_date(f).is_some_and(|d| d >= since));
+    }
+    Ok(files)
+}
+
+/// Span covered by `summarize --digest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DigestPeriod {
+    Weekly,
+    Monthly,
+}
+
+impl DigestPeriod {
+    /// First and last day of the week (Monday to Sunday) or month containing `today`.
+    pub fn range(self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            DigestPeriod::Weekly => {
+                let start = today - Days::new(today.weekday().num_days_from_monday().into());
+                (start, start + Days::new(6))
+            }
+            DigestPeriod::Monthly => {
+                let start = today.with_day(1).unwrap_or(today);
+                let next = start
+                    .checked_add_months(chrono::Months::new(1))
+                    .unwrap_or(start);
+                (start, next.pred_opt().unwrap_or(start))
+            }
+        }
+    }
+
+    /// Entry title for the digest: `2024-w23-digest.md` (ISO week) or `2024-06-digest.md`.
+    pub fn title(self, today: NaiveDate) -> String {
+        match self {
+            DigestPeriod::Weekly => {
+                let week = today.iso_week();
+                format!("{}-w{:02}-digest.md", week.year(), week.week())
+            }
+            DigestPeriod::Monthly => format!("{}-digest.md", today.format("%Y-%m")),
+        }
+    }
+}
+
+/// Keep the files dated between `start` and `end`, both inclusive.
+pub fn entries_between(files: Vec<PathBuf>, start: NaiveDate, end: NaiveDate) -> Vec<PathBuf> {
+    files
+        .into_iter()
+        .filter(|f| entry_date(f).is_some_and(|d| d >= start && d <= end))
+        .collect()
+}
+
+/// Concatenate files, each preceded by a `## path` header so the model can tell
+/// entries apart.
+pub fn read_files(files: &[PathBuf]) -> Result<String> {
+    let mut out = String::new();
+    for file in files {
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        out.push_str(&format!("## {}\n\n{}\n\n", file.display(), content.trim()));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_date_from_prefix_and_layout() {
+        assert_eq!(
+            entry_date(Path::new("/notes/2026-02-25-team-sync.md")),
+            NaiveDate::from_ymd_opt(2026, 2, 25)
+        );
+        assert_eq!(
+            entry_date(Path::new("/journals/2026/02/07-081500-standup.md")),
+            NaiveDate::from_ymd_opt(2026, 2, 7)
+        );
+    }
+
+    #[test]
+    fn test_collect_files_since_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("2026/02")).unwrap();
+        fs::write(root.join("2026/02/01-080000-old.md"), "old").unwrap();
+        fs::write(root.join("2026/02/20-080000-new.md"), "new").unwrap();
+        fs::write(root.join("2026-03-01-flat.md"), "flat").unwrap();
+
+        let since = NaiveDate::from_ymd_opt(2026, 2, 10);
+        let files = collect_files(root, since, "md").unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["20-080000-new.md", "2026-03-01-flat.md"]);
+
+        assert_eq!(collect_files(root, None, "md").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_collect_files_single_file_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("note.md");
+        fs::write(&file, "Hello\n").unwrap();
+
+        let files = collect_files(&file, None, "md").unwrap();
+        let content = read_files(&files).unwrap();
+        assert!(content.contains("note.md"));
+        assert!(content.contains("Hello"));
+    }
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_digest_ranges_and_titles() {
+        // Wednesday 2024-06-05 is in ISO week 23
+        let today = day(2024, 6, 5);
+        assert_eq!(
+            DigestPeriod::Weekly.range(today),
+            (day(2024, 6, 3), day(2024, 6, 9))
+        );
+        assert_eq!(DigestPeriod::Weekly.title(today), "2024-w23-digest.md");
+        assert_eq!(
+            DigestPeriod::Monthly.range(today),
+            (day(2024, 6, 1), day(2024, 6, 30))
+        );
+        assert_eq!(DigestPeriod::Monthly.title(today), "2024-06-digest.md");
+
+        // A week spanning the new year belongs to ISO week 1 of the next year
+        let today = day(2024, 12, 31);
+        assert_eq!(
+            DigestPeriod::Weekly.range(today),
+            (day(2024, 12, 30), day(2025, 1, 5))
+        );
+        assert_eq!(DigestPeriod::Weekly.title(today), "2025-w01-digest.md");
+        assert_eq!(
+            DigestPeriod::Monthly.range(today),
+            (day(2024, 12, 1), day(2024, 12, 31))
+        );
+        assert_eq!(
+            DigestPeriod::Monthly.range(day(2024, 2, 10)).1,
+            day(2024, 2, 29)
+        );
+    }
+
+    #[test]
+    fn test_entries_between_week_and_month_boundaries() {
+        let files: Vec<PathBuf> = [
+            "/j/2024/06/02-230000-sunday-before.md",
+            "/j/2024/06/03-080000-monday.md",
+            "/j/2024/06/09-235900-sunday.md",
+            "/j/2024/06/10-000100-next-monday.md",
+            "/j/2024/05/31-120000-last-of-may.md",
+            "/j/2024/06/30-120000-last-of-june.md",
+            "/j/2024/07/01-120000-first-of-july.md",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        let names = |files: Vec<PathBuf>| -> Vec<String> {
+            files
+                .iter()
+                .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+
+        let (start, end) = DigestPeriod::Weekly.range(day(2024, 6, 5));
+        assert_eq!(
+            names(entries_between(files.clone(), start, end)),
+            vec!["03-080000-monday.md", "09-235900-sunday.md"]
+        );
+
+        let (start, end) = DigestPeriod::Monthly.range(day(2024, 6, 5));
+        assert_eq!(
+            names(entries_between(files, start, end)),
+            vec![
+                "02-230000-sunday-before.md",
+                "03-080000-monday.md",
+                "09-235900-sunday.md",
+                "10-000100-next-monday.md",
+                "30-120000-last-of-june.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_files_missing_path() {
+        assert!(collect_files(Path::new("/does/not/exist"), None, "md").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::journal;
+
+/// Entry filenames (relative to the journal root) by tag.
+type Tags = BTreeMap<String, BTreeSet<String>>;
+
+/// Index of which entries carry which tag, stored as one JSON file. Updates hold a lock
+/// and replace the file atomically, so parallel batch jobs and separate runs don't lose
+/// updates and readers never see half of one.
+pub struct TagIndex {
+    path: PathBuf,
+}
+
+/// Tags compare case-insensitively and with or without a leading `#`.
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+fn normalize_tags(tags: &[String]) -> impl Iterator<Item = String> + '_ {
+    tags.iter()
+        .map(|tag| normalize_tag(tag))
+        .filter(|tag| !tag.is_empty())
+}
+
+impl TagIndex {
+    /// `tags.json` in the config directory.
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(config::app_dir()?.join("tags.json"))
+    }
+
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Record `filename` under each of `tags`.
+    pub fn add(&self, filename: &str, tags: &[String]) -> Result<()> {
+        self.update(|index| {
+            for tag in normalize_tags(tags) {
+                index.entry(tag).or_default().insert(filename.to_string());
+            }
+        })
+    }
+
+    /// Filenames tagged `tag`, in path (so chronological) order.
+    pub fn query(&self, tag: &str) -> Result<Vec<String>> {
+        let index = self.read()?;
+        Ok(index
+            .get(&normalize_tag(tag))
+            .map(|files| files.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// Replace the index with the frontmatter tags of every `.<extension>` entry under
+    /// `journal_root`. Returns how many entries were scanned.
+    pub fn rebuild(&self, journal_root: &Path, extension: &str) -> Result<usize> {
+        let entries = journal::list_entries(journal_root, extension)?;
+        let mut rebuilt = Tags::new();
+        for path in &entries {
+            let summary = journal::EntrySummary::from_path(path.clone());
+            let filename = path
+                .strip_prefix(journal_root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string();
+            for tag in normalize_tags(&summary.tags) {
+                rebuilt.entry(tag).or_default().insert(filename.clone());
+            }
+        }
+        self.update(|index| *index = rebuilt)?;
+        Ok(entries.len())
+    }
+
+    /// Read, change and write the index while holding an exclusive lock on it.
+    fn update(&self, change: impl FnOnce(&mut Tags)) -> Result<()> {
+        journal::with_file_lock(&self.path, || {
+            let mut index = self.read()?;
+            change(&mut index);
+            journal::write_atomic(&self.path, &serde_json::to_string_pretty(&index)?)
+                .with_context(|| format!("Failed to write tag index {}", self.path.display()))
+        })
+    }
+
+    /// The index on disk; a missing or empty file is an empty index.
+    fn read(&self) -> Result<Tags> {
+        if !self.exists() {
+            return Ok(Tags::new());
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read tag index {}", self.path.display()))?;
+        if content.trim().is_empty() {
+            return Ok(Tags::new());
+        }
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse tag index {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_add_and_query_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = TagIndex::new(dir.path().join("state").join("tags.json"));
+        assert!(index.query("work").unwrap().is_empty());
+
+        index
+            .add("2026/03/02-090000-standup.md", &tags(&["work", "#Q2"]))
+            .unwrap();
+        index
+            .add("2026/03/01-180000-gym.md", &tags(&["health"]))
+            .unwrap();
+        index
+            .add("2026/03/01-080000-planning.md", &tags(&["work"]))
+            .unwrap();
+
+        let index = TagIndex::new(dir.path().join("state").join("tags.json"));
+        assert_eq!(
+            index.query("#Work").unwrap(),
+            [
+                "2026/03/01-080000-planning.md",
+                "2026/03/02-090000-standup.md"
+            ]
+        );
+        assert_eq!(index.query("q2").unwrap(), ["2026/03/02-090000-standup.md"]);
+        assert!(index.query("travel").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parallel_adds_are_all_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tags.json");
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let path = path.clone();
+                scope.spawn(move || {
+                    TagIndex::new(path)
+                        .add(&format!("entry-{}.md", i), &tags(&["batch"]))
+                        .unwrap()
+                });
+            }
+        });
+        assert_eq!(TagIndex::new(path).query("batch").unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_rebuild_from_journal_directory() {
+        let journal = tempfile::tempdir().unwrap();
+        let month = journal.path().join("2026").join("03");
+        fs::create_dir_all(&month).unwrap();
+        fs::write(
+            month.join("01-080000-planning.md"),
+            "---\ntags:\n- work\n- q2\n---\nPlan Q2.\n",
+        )
+        .unwrap();
+        fs::write(month.join("02-180000-gym.md"), "No frontmatter here.\n").unwrap();
+
+        let state = tempfile::tempdir().unwrap();
+        let index = TagIndex::new(state.path().join("tags.json"));
+        index.add("stale.md", &tags(&["work"])).unwrap();
+
+        assert_eq!(index.rebuild(journal.path(), "md").unwrap(), 2);
+        assert_eq!(
+            index.query("work").unwrap(),
+            ["2026/03/01-080000-planning.md"]
+        );
+        assert_eq!(
+            index.query("q2").unwrap(),
+            ["2026/03/01-080000-planning.md"]
+        );
+    }
+}
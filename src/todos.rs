@@ -1,10 +1,11 @@
 use anyhow::{Context, Result};
-use chrono::{SecondsFormat, Utc};
+use chrono::SecondsFormat;
 use serde::Serialize;
 use std::fs;
 use std::path::{Path, PathBuf};
 use ulid::Ulid;
 
+use crate::clock;
 use crate::providers::TaskItem;
 
 #[derive(Debug, Serialize)]
@@ -64,10 +65,12 @@ fn normalize_priority(p: &str) -> &str {
 ///
 /// journal_root: path to journal root (e.g., ~/Documents/journals)
 /// linked_note: relative path like 2026/02/25-220255-title.md
+/// use_utc: stamp filenames and timestamps in UTC instead of local time
 pub fn save_todos(
     journal_root: &Path,
     linked_note: &str,
     tasks: &[TaskItem],
+    use_utc: bool,
 ) -> Result<Vec<PathBuf>> {
     if tasks.is_empty() {
         return Ok(vec![]);
@@ -81,7 +84,7 @@ pub fn save_todos(
 
     for task in tasks {
         let id = Ulid::new();
-        let now = Utc::now();
+        let now = clock::now(use_utc);
         let now_iso = now.to_rfc3339_opts(SecondsFormat::Secs, true);
         let ts_prefix = now.format("%Y-%m-%d_%H%M%S").to_string();
 
@@ -132,7 +135,7 @@ mod tests {
             due: Some("2026-02-27".to_string()),
         }];
 
-        let written = save_todos(root, "2026/02/25-220255-test.md", &tasks).unwrap();
+        let written = save_todos(root, "2026/02/25-220255-test.md", &tasks, false).unwrap();
         assert_eq!(written.len(), 1);
 
         let content = std::fs::read_to_string(&written[0]).unwrap();